@@ -15,7 +15,7 @@ fn main() {
     }
 
     let tokenizer = Tokenizer::tokenize(&args[1]);
-    let mut parser = Parser::parse(tokenizer);
+    let parser = Parser::parse(tokenizer);
     let mut generator = CodeGen::init();
-    generator.generate(&mut parser)
+    generator.generate(&parser)
 }
@@ -1,27 +1,599 @@
-use super::parser::{Node, NodeKind, Parser};
+use super::parser::{Function, GlobalVar, LVar, Node, NodeKind, Parser, VA_AREA_SIZE};
+use super::types::{align_to, Member, Type, TypeKind};
+use std::collections::{HashMap, HashSet};
 use std::process;
 
+// Registers for the integer arguments of a call, by operand size
+const ARG_REGS64: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+const ARG_REGS32: [&str; 6] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+const ARG_REGS16: [&str; 6] = ["di", "si", "dx", "cx", "r8w", "r9w"];
+const ARG_REGS8: [&str; 6] = ["dil", "sil", "dl", "cl", "r8b", "r9b"];
+// and the number of xmm registers for the floating-point ones
+const FP_ARG_REGS: usize = 8;
+// Registers for the general-purpose eightbytes of a returned struct
+const RET_REGS64: [&str; 2] = ["rax", "rdx"];
+const RET_REGS8: [&str; 2] = ["al", "dl"];
+
 pub struct CodeGen {
     jmp_counter: i64,
+    function_name: String,
+    // jump targets of the enclosing loops and switches, innermost last, with
+    // the stack depth at each
+    break_labels: Vec<(String, i64)>,
+    continue_labels: Vec<(String, i64)>,
+    switch_labels: Vec<i64>,
+    // number of values currently pushed on the stack by expressions
+    depth: i64,
+    // the depth statements start at, which is above zero in a statement
+    // expression
+    stmt_depth: i64,
+    // the depth at each variable-length array, by frame offset, which is
+    // where freeing it restores the stack pointer to
+    vla_depths: HashMap<usize, i64>,
+    // for va_start in a variadic function: the number of named parameters
+    // passed in general-purpose registers, xmm registers and on the stack,
+    // and the offset of the register save area
+    named_args: (usize, usize, usize),
+    va_area_offset: usize,
+    // for a function returning a struct in memory: the offset of the saved
+    // address to return it to
+    ret_ptr_offset: Option<usize>,
+    // whether the frame was realigned for a local aligned beyond 16 bytes
+    realigned: bool,
+    // the functions declared but not defined in this file, whose address is
+    // read from the GOT since another object may define them
+    extern_functions: HashSet<String>,
 }
 
 impl CodeGen {
     pub fn init() -> Self {
-        CodeGen { jmp_counter: 0 }
+        CodeGen {
+            jmp_counter: 0,
+            function_name: String::new(),
+            break_labels: Vec::new(),
+            continue_labels: Vec::new(),
+            switch_labels: Vec::new(),
+            depth: 0,
+            stmt_depth: 0,
+            vla_depths: HashMap::new(),
+            named_args: (0, 0, 0),
+            va_area_offset: 0,
+            ret_ptr_offset: None,
+            realigned: false,
+            extern_functions: HashSet::new(),
+        }
+    }
+
+    fn new_label(&mut self) -> i64 {
+        self.jmp_counter += 1;
+        self.jmp_counter
+    }
+
+    fn gen_loop_body(&mut self, body: Node, label: i64) {
+        self.break_labels
+            .push((format!(".L.end.{}", label), self.depth));
+        self.continue_labels
+            .push((format!(".L.continue.{}", label), self.depth));
+        self.gen_stmt(body);
+        self.break_labels.pop();
+        self.continue_labels.pop();
+    }
+
+    // Jump from the switch value in rax to the matching case label. Dense case
+    // sets go through a jump table, sparse ones through comparisons.
+    fn gen_switch_dispatch(
+        &mut self,
+        label: i64,
+        cases: &[i64],
+        has_default: bool,
+        is_unsigned: bool,
+    ) {
+        let fallback = if has_default {
+            format!(".L.default.{}", label)
+        } else {
+            format!(".L.end.{}", label)
+        };
+        // order the cases the way the switch value compares
+        let key = |val: i64| {
+            if is_unsigned {
+                val as u64 as i128
+            } else {
+                val as i128
+            }
+        };
+        let mut sorted: Vec<(i64, usize)> = cases.iter().cloned().zip(0..).collect();
+        sorted.sort_by_key(|(val, _)| key(*val));
+
+        // a jump table pays off when it has at least 4 entries and at most a
+        // third of its slots fall through to the default
+        let slots = match (sorted.first(), sorted.last()) {
+            (Some(min), Some(max)) => key(max.0) - key(min.0) + 1,
+            _ => 0,
+        };
+        if sorted.len() >= 4 && slots <= sorted.len() as i128 * 3 / 2 {
+            self.gen_jump_table(label, &sorted, &fallback);
+        } else {
+            let jump_greater = if is_unsigned { "ja" } else { "jg" };
+            self.gen_switch_search(label, &sorted, &fallback, jump_greater);
+        }
+    }
+
+    fn gen_jump_table(&mut self, label: i64, sorted: &[(i64, usize)], fallback: &str) {
+        let min = sorted.first().unwrap().0;
+        let max = sorted.last().unwrap().0;
+
+        // rax - min is out of range as an unsigned number if rax < min
+        println!("  mov rdi, {}", min);
+        println!("  sub rax, rdi");
+        println!("  mov rdi, {}", max.wrapping_sub(min));
+        println!("  cmp rax, rdi");
+        println!("  ja {}", fallback);
+        println!("  lea rdi, [rip + .L.jt.{}]", label);
+        println!("  movsxd rax, dword ptr [rdi + rax * 4]");
+        println!("  add rax, rdi");
+        println!("  jmp rax");
+
+        // entries are offsets from the table so that it needs no relocations
+        println!("  .section .rodata");
+        println!("  .align 4");
+        println!(".L.jt.{}:", label);
+        let mut cases = sorted.iter().peekable();
+        let mut val = min;
+        loop {
+            match cases.peek() {
+                Some((case_val, index)) if *case_val == val => {
+                    println!("  .long .L.case.{}.{} - .L.jt.{}", label, index, label);
+                    cases.next();
+                }
+                _ => println!("  .long {} - .L.jt.{}", fallback, label),
+            }
+            if val == max {
+                break;
+            }
+            val = val.wrapping_add(1);
+        }
+        println!("  .text");
+    }
+
+    // Binary search over the sorted case values, with a compare chain once
+    // only a few cases are left.
+    fn gen_switch_search(
+        &mut self,
+        label: i64,
+        sorted: &[(i64, usize)],
+        fallback: &str,
+        jump_greater: &str,
+    ) {
+        if sorted.len() <= 4 {
+            for (val, index) in sorted.iter() {
+                println!("  mov rdi, {}", val);
+                println!("  cmp rax, rdi");
+                println!("  je .L.case.{}.{}", label, index);
+            }
+            println!("  jmp {}", fallback);
+            return;
+        }
+
+        let mid = sorted.len() / 2;
+        let (val, index) = sorted[mid];
+        let upper = self.new_label();
+        println!("  mov rdi, {}", val);
+        println!("  cmp rax, rdi");
+        println!("  je .L.case.{}.{}", label, index);
+        println!("  {} .L.search.{}", jump_greater, upper);
+        self.gen_switch_search(label, &sorted[..mid], fallback, jump_greater);
+        println!(".L.search.{}:", upper);
+        self.gen_switch_search(label, &sorted[mid + 1..], fallback, jump_greater);
+    }
+
+    // Allocate rax bytes on the stack and leave their address in rax. The
+    // values pushed by the enclosing expressions are copied below the new
+    // area, so that they are still on top of the stack. The originals stay
+    // where they were, for when the area is freed by restoring the stack
+    // pointer.
+    fn gen_alloca(&mut self) {
+        let pushed = self.depth * 8;
+        println!("  add rax, {}", pushed + 15);
+        println!("  and rax, -16");
+        println!("  sub rsp, rax");
+        for i in 0..self.depth {
+            println!("  mov rdx, [rsp + rax + {}]", i * 8);
+            println!("  mov [rsp + {}], rdx", i * 8);
+        }
+        println!("  lea rax, [rsp + {}]", pushed);
+    }
+
+    // Free the variable-length arrays allocated since `vla`, the first of
+    // the ones a block or jump leaves the scope of, by restoring the stack
+    // pointer saved before it.
+    fn free_vlas(vla: &Option<LVar>) {
+        if let Some(vla) = vla {
+            println!("  mov rsp, [rbp - {}]", vla.offset - 8);
+        }
+    }
+
+    // Jump to `label`, whose statement starts at the stack depth `depth`, an
+    // assembler expression counting bytes. A jump out of a statement
+    // expression leaves the values the enclosing expressions pushed, so they
+    // are dropped, after freeing the variable-length arrays it leaves.
+    fn gen_jump(&self, label: &str, vla: &Option<LVar>, depth: &str) {
+        CodeGen::free_vlas(vla);
+        let from = match vla {
+            Some(vla) => self.vla_depths[&vla.offset],
+            None => self.depth,
+        };
+        // no jump enters a statement expression, so a jump from depth 0
+        // goes to depth 0
+        if from > 0 && (from * 8).to_string() != depth {
+            println!("  lea rsp, [rsp + {} - {}]", from * 8, depth);
+        }
+        println!("  jmp {}", label);
+    }
+
+    fn push(&mut self, operand: &str) {
+        println!("  push {}", operand);
+        self.depth += 1;
+    }
+
+    fn pop(&mut self, reg: &str) {
+        println!("  pop {}", reg);
+        self.depth -= 1;
+    }
+
+    // Statements leave the stack as they found it, so at a statement boundary
+    // nothing may be left pushed. A violation is a bug in the code generator
+    // rather than in the input, so it is checked in debug builds only.
+    fn check_depth(&self, kind: &NodeKind) {
+        if cfg!(debug_assertions) && self.depth != self.stmt_depth {
+            eprintln!(
+                "internal compiler error: stack depth is {} at {:?}",
+                self.depth, kind
+            );
+            process::exit(1);
+        }
+    }
+
+    // Evaluate a condition and jump to `label` if it is zero.
+    fn gen_branch_if_zero(&mut self, cond: Node, label: &str) {
+        let ty = cond.ty.clone().unwrap();
+        self.gen_expr(cond);
+        self.pop("rax");
+        CodeGen::cmp_zero(&ty);
+        println!("  je {}", label);
+    }
+
+    // Set the flags so that "je" jumps if rax, of type `ty`, is zero. A
+    // floating-point zero may be negative, and NaN is not zero.
+    fn cmp_zero(ty: &Type) {
+        if !ty.is_flonum() {
+            println!("  cmp rax, 0");
+            return;
+        }
+        let suffix = CodeGen::sse_suffix(ty);
+        println!("  movq xmm0, rax");
+        println!("  xorps xmm1, xmm1");
+        println!("  ucomi{} xmm0, xmm1", suffix);
+        println!("  setne al");
+        println!("  setp dl");
+        println!("  or al, dl");
+        println!("  cmp al, 0");
+    }
+
+    // The suffix of the scalar SSE instructions operating on `ty`.
+    fn sse_suffix(ty: &Type) -> &'static str {
+        if ty.kind == TypeKind::TyFloat {
+            "ss"
+        } else {
+            "sd"
+        }
+    }
+
+    // Move a floating-point result from xmm0 to rax. A float is kept in the
+    // low 32 bits, zero-extended like an unsigned int.
+    fn from_xmm0(ty: &Type) {
+        if ty.kind == TypeKind::TyFloat {
+            println!("  movd eax, xmm0");
+        } else {
+            println!("  movq rax, xmm0");
+        }
+    }
+
+    // Load a value of type `ty` from the address in rax. Values narrower than
+    // 64 bits are sign- or zero-extended, so that rax always holds the value
+    // of its type in full.
+    fn load(ty: &Type) {
+        // a struct is handled through its address, so that it needs no
+        // register to fit in, an array is the address of its first element,
+        // and a function is its own address
+        if ty.is_struct() || ty.is_array() || ty.is_function() {
+            return;
+        }
+        if ty.is_flonum() {
+            match ty.size {
+                4 => println!("  mov eax, dword ptr [rax]"),
+                _ => println!("  mov rax, [rax]"),
+            }
+            return;
+        }
+        match (ty.size, ty.is_unsigned) {
+            (1, false) => println!("  movsx rax, byte ptr [rax]"),
+            (1, true) => println!("  movzx eax, byte ptr [rax]"),
+            (2, false) => println!("  movsx rax, word ptr [rax]"),
+            (2, true) => println!("  movzx eax, word ptr [rax]"),
+            (4, false) => println!("  movsxd rax, dword ptr [rax]"),
+            (4, true) => println!("  mov eax, dword ptr [rax]"),
+            _ => println!("  mov rax, [rax]"),
+        }
+    }
+
+    // Store rdi to the address in rax. For a struct, rdi is the address of
+    // the struct to copy.
+    fn store(ty: &Type) {
+        if ty.is_struct() {
+            for offset in 0..ty.size {
+                println!("  mov r8b, [rdi + {}]", offset);
+                println!("  mov [rax + {}], r8b", offset);
+            }
+            return;
+        }
+        match ty.size {
+            1 => println!("  mov [rax], dil"),
+            2 => println!("  mov [rax], di"),
+            4 => println!("  mov [rax], edi"),
+            _ => println!("  mov [rax], rdi"),
+        }
+    }
+
+    // The member an assignment stores to, if it is a bit-field.
+    fn bitfield_of(lhs: &Node) -> Option<Member> {
+        lhs.member.clone().filter(|member| member.is_bitfield)
+    }
+
+    // Load a bit-field from its storage unit at the address in rax, by
+    // shifting its bits to the top and back down, extending them.
+    fn load_bitfield(member: &Member) {
+        CodeGen::load(&member.ty);
+        let shift = 64 - member.bit_width;
+        println!("  shl rax, {}", shift - member.bit_offset);
+        if member.ty.is_unsigned {
+            println!("  shr rax, {}", shift);
+        } else {
+            println!("  sar rax, {}", shift);
+        }
+    }
+
+    // Store rdi to a bit-field in the storage unit at the address in rax,
+    // keeping the other bits of the unit. The value of the assignment, the
+    // one the bit-field now holds, is left in rdi.
+    fn store_bitfield(member: &Member) {
+        let mask = member.bit_mask();
+        println!("  mov r8, rax");
+        println!("  mov rdx, {}", mask as i64);
+        println!("  and rdi, rdx");
+        println!("  mov r9, rdi");
+        println!("  shl rdi, {}", member.bit_offset);
+        CodeGen::load(&member.ty);
+        println!("  mov rdx, {}", !(mask << member.bit_offset) as i64);
+        println!("  and rax, rdx");
+        println!("  or rdi, rax");
+        println!("  mov rax, r8");
+        CodeGen::store(&member.ty);
+
+        println!("  mov rdi, r9");
+        if !member.ty.is_unsigned {
+            let shift = 64 - member.bit_width;
+            println!("  shl rdi, {}", shift);
+            println!("  sar rdi, {}", shift);
+        }
+    }
+
+    // The general-purpose and xmm registers a value of type `ty` is passed
+    // in, or None if it is passed in memory.
+    fn reg_count(ty: &Type) -> Option<(usize, usize)> {
+        if !ty.is_struct() {
+            return Some(if ty.is_flonum() { (0, 1) } else { (1, 0) });
+        }
+        let classes = ty.eightbytes()?;
+        let fp = classes.iter().filter(|&&is_sse| is_sse).count();
+        Some((classes.len() - fp, fp))
     }
 
+    // The offset and size of each eightbyte of a struct, and whether it
+    // goes in an xmm register.
+    fn struct_eightbytes(ty: &Type) -> Vec<(usize, usize, bool)> {
+        let classes = ty.eightbytes().unwrap();
+        classes
+            .into_iter()
+            .enumerate()
+            .map(|(i, is_sse)| (i * 8, (ty.size - i * 8).min(8), is_sse))
+            .collect()
+    }
+
+    // Load the eightbytes of a struct at the address in r11 to the next of
+    // the given general-purpose registers and xmm registers. A partial
+    // eightbyte is read byte by byte, so that nothing past the struct is.
+    fn load_struct(ty: &Type, regs64: &[&str], regs8: &[&str], gp: &mut usize, fp: &mut usize) {
+        for (offset, size, is_sse) in CodeGen::struct_eightbytes(ty) {
+            if is_sse {
+                let mov = if size == 4 { "movss" } else { "movsd" };
+                println!("  {} xmm{}, [r11 + {}]", mov, fp, offset);
+                *fp += 1;
+                continue;
+            }
+            if size == 8 {
+                println!("  mov {}, [r11 + {}]", regs64[*gp], offset);
+            } else {
+                for i in (0..size).rev() {
+                    println!("  shl {}, 8", regs64[*gp]);
+                    println!("  mov {}, [r11 + {}]", regs8[*gp], offset + i);
+                }
+            }
+            *gp += 1;
+        }
+    }
+
+    // Store the eightbytes of a struct from registers, the reverse of
+    // load_struct.
+    fn store_struct(ty: &Type, regs64: &[&str], regs8: &[&str], gp: &mut usize, fp: &mut usize) {
+        for (offset, size, is_sse) in CodeGen::struct_eightbytes(ty) {
+            if is_sse {
+                let mov = if size == 4 { "movss" } else { "movsd" };
+                println!("  {} [r11 + {}], xmm{}", mov, offset, fp);
+                *fp += 1;
+                continue;
+            }
+            if size == 8 {
+                println!("  mov [r11 + {}], {}", offset, regs64[*gp]);
+            } else {
+                for i in 0..size {
+                    println!("  mov [r11 + {}], {}", offset + i, regs8[*gp]);
+                    println!("  shr {}, 8", regs64[*gp]);
+                }
+            }
+            *gp += 1;
+        }
+    }
+
+    // Convert rax to `ty` by truncating it and extending it back to 64 bits.
+    // The representation of a value of any integer type is the same as that
+    // of the 64-bit integer it converts to, so widening needs no code.
+    fn truncate(ty: &Type) {
+        if !ty.is_integer() {
+            return;
+        }
+        match (ty.size, ty.is_unsigned) {
+            (1, false) => println!("  movsx rax, al"),
+            (1, true) => println!("  movzx eax, al"),
+            (2, false) => println!("  movsx rax, ax"),
+            (2, true) => println!("  movzx eax, ax"),
+            (4, false) => println!("  movsxd rax, eax"),
+            (4, true) => println!("  mov eax, eax"),
+            _ => {}
+        }
+    }
+
+    // Convert rax from `from` to `to`. Floating-point values are passed
+    // through rax as their bit patterns and converted in xmm0.
+    fn gen_cast(&mut self, from: &Type, to: &Type) {
+        if to.kind == TypeKind::TyVoid {
+            return;
+        }
+        // any nonzero value, not just its low byte, converts to true
+        if to.kind == TypeKind::TyBool {
+            CodeGen::cmp_zero(from);
+            println!("  setne al");
+            println!("  movzx eax, al");
+            return;
+        }
+        if !from.is_flonum() && !to.is_flonum() {
+            CodeGen::truncate(to);
+            return;
+        }
+
+        if from.is_flonum() && to.is_flonum() {
+            if from.kind != to.kind {
+                println!("  movq xmm0, rax");
+                println!(
+                    "  cvt{}2{} xmm0, xmm0",
+                    CodeGen::sse_suffix(from),
+                    CodeGen::sse_suffix(to)
+                );
+                CodeGen::from_xmm0(to);
+            }
+            return;
+        }
+
+        if to.is_flonum() {
+            let suffix = CodeGen::sse_suffix(to);
+            if from.size == 8 && from.is_unsigned {
+                // cvtsi2sd only takes signed integers, so a value with the
+                // top bit set is halved, keeping its lowest bit so that it
+                // rounds the same, and doubled back after the conversion
+                let label = self.new_label();
+                println!("  test rax, rax");
+                println!("  js .L.cast.{}", label);
+                println!("  cvtsi2{} xmm0, rax", suffix);
+                println!("  jmp .L.end.{}", label);
+                println!(".L.cast.{}:", label);
+                println!("  mov rdi, rax");
+                println!("  and edi, 1");
+                println!("  shr rax, 1");
+                println!("  or rax, rdi");
+                println!("  cvtsi2{} xmm0, rax", suffix);
+                println!("  add{} xmm0, xmm0", suffix);
+                println!(".L.end.{}:", label);
+            } else {
+                println!("  cvtsi2{} xmm0, rax", suffix);
+            }
+            CodeGen::from_xmm0(to);
+            return;
+        }
+
+        let suffix = CodeGen::sse_suffix(from);
+        println!("  movq xmm0, rax");
+        if to.size == 8 && to.is_unsigned {
+            // values from 2^63 up do not fit cvttsd2si, so they are
+            // converted less 2^63 and the top bit is set afterwards
+            let label = self.new_label();
+            let two_pow_63 = if from.kind == TypeKind::TyFloat {
+                (2f32.powi(63).to_bits() as u64).to_string()
+            } else {
+                2f64.powi(63).to_bits().to_string()
+            };
+            println!("  mov rax, {}", two_pow_63);
+            println!("  movq xmm1, rax");
+            println!("  ucomi{} xmm0, xmm1", suffix);
+            println!("  jae .L.cast.{}", label);
+            println!("  cvtt{}2si rax, xmm0", suffix);
+            println!("  jmp .L.end.{}", label);
+            println!(".L.cast.{}:", label);
+            println!("  sub{} xmm0, xmm1", suffix);
+            println!("  cvtt{}2si rax, xmm0", suffix);
+            println!("  btc rax, 63");
+            println!(".L.end.{}:", label);
+        } else {
+            println!("  cvtt{}2si rax, xmm0", suffix);
+            CodeGen::truncate(to);
+        }
+    }
+
+    // Push the address of an lvalue.
     fn gen_lval(&mut self, node: Node) {
         match node.kind {
             NodeKind::NodeLVar => {
-                //println!("LVAR");
-                println!("  mov rax, rbp");
-                println!("  sub rax, {}", node.offset);
-                println!("  push rax");
-                return;
+                let var = node.var.unwrap();
+                if var.is_global && self.extern_functions.contains(&var.name) {
+                    println!("  mov rax, [rip + {}@GOTPCREL]", var.name);
+                } else if var.is_global {
+                    println!("  lea rax, [rip + {}]", var.name);
+                } else if var.ty.is_vla() {
+                    println!("  mov rax, [rbp - {}]", var.offset);
+                } else {
+                    println!("  mov rax, rbp");
+                    println!("  sub rax, {}", var.offset);
+                }
+                self.push("rax");
             }
             NodeKind::NodeDeref => {
-                self.gen_stmt(*node.lhs.unwrap());
-                return;
+                self.gen_expr(*node.lhs.unwrap());
+            }
+            NodeKind::NodeMember => {
+                self.gen_lval(*node.lhs.unwrap());
+                self.pop("rax");
+                println!("  add rax, {}", node.member.unwrap().offset);
+                self.push("rax");
+            }
+            // a compound literal is its initialization followed by the object
+            NodeKind::NodeCompoundLiteral => {
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_lval(*node.rhs.unwrap());
+            }
+            // the value of a struct is its address already
+            NodeKind::NodeStmtExpr | NodeKind::NodeComma | NodeKind::NodeFuncall
+                if node.ty.as_ref().unwrap().is_struct() =>
+            {
+                self.gen_expr(node);
             }
             _ => {
                 eprint!("The left side value of assignment is not a variable");
@@ -31,23 +603,248 @@ impl CodeGen {
     }
 
     fn gen_stmt(&mut self, node: Node) {
+        self.check_depth(&node.kind);
+        let kind = node.kind.clone();
+
+        match node.kind {
+            NodeKind::NodeBlock => {
+                for stmt in node.body.into_iter() {
+                    self.gen_stmt(*stmt.unwrap());
+                }
+                CodeGen::free_vlas(&node.var);
+            }
+            NodeKind::NodeIf => {
+                let label = self.new_label();
+                self.gen_branch_if_zero(*node.cond.unwrap(), &format!(".L.else.{}", label));
+                self.gen_stmt(*node.then.unwrap());
+                println!("  jmp .L.end.{}", label);
+                println!(".L.else.{}:", label);
+                if let Some(els) = node.els {
+                    self.gen_stmt(*els);
+                }
+                println!(".L.end.{}:", label);
+            }
+            NodeKind::NodeFor => {
+                let label = self.new_label();
+                if let Some(init) = node.init {
+                    self.gen_stmt(*init);
+                }
+                println!(".L.begin.{}:", label);
+                if let Some(cond) = node.cond {
+                    self.gen_branch_if_zero(*cond, &format!(".L.end.{}", label));
+                }
+                self.gen_loop_body(*node.then.unwrap(), label);
+                println!(".L.continue.{}:", label);
+                if let Some(inc) = node.inc {
+                    self.gen_stmt(*inc);
+                }
+                println!("  jmp .L.begin.{}", label);
+                println!(".L.end.{}:", label);
+            }
+            NodeKind::NodeDo => {
+                let label = self.new_label();
+                println!(".L.begin.{}:", label);
+                self.gen_loop_body(*node.then.unwrap(), label);
+                println!(".L.continue.{}:", label);
+                let cond = *node.cond.unwrap();
+                let ty = cond.ty.clone().unwrap();
+                self.gen_expr(cond);
+                self.pop("rax");
+                CodeGen::cmp_zero(&ty);
+                println!("  jne .L.begin.{}", label);
+                println!(".L.end.{}:", label);
+            }
+            NodeKind::NodeBreak => match self.break_labels.last() {
+                Some((label, depth)) => {
+                    self.gen_jump(label, &node.var, &(depth * 8).to_string());
+                }
+                None => {
+                    eprintln!("codegen: break statement not within a loop or switch");
+                    process::exit(1);
+                }
+            },
+            NodeKind::NodeContinue => match self.continue_labels.last() {
+                Some((label, depth)) => {
+                    self.gen_jump(label, &node.var, &(depth * 8).to_string());
+                }
+                None => {
+                    eprintln!("codegen: continue statement not within a loop");
+                    process::exit(1);
+                }
+            },
+            NodeKind::NodeSwitch => {
+                let label = self.new_label();
+                let is_unsigned = node.cond.as_ref().unwrap().ty.as_ref().unwrap().is_unsigned;
+                self.gen_expr(*node.cond.unwrap());
+                self.pop("rax");
+                self.gen_switch_dispatch(label, &node.cases, node.has_default, is_unsigned);
+
+                self.break_labels
+                    .push((format!(".L.end.{}", label), self.depth));
+                self.switch_labels.push(label);
+                self.gen_stmt(*node.then.unwrap());
+                self.break_labels.pop();
+                self.switch_labels.pop();
+                println!(".L.end.{}:", label);
+            }
+            NodeKind::NodeCase => {
+                let label = self.switch_labels.last().unwrap();
+                match node.case_index {
+                    Some(index) => println!(".L.case.{}.{}:", label, index),
+                    None => println!(".L.default.{}:", label),
+                }
+                self.gen_stmt(*node.lhs.unwrap());
+            }
+            // the depth at a label may only be known after a goto to it, so
+            // it is an assembler symbol
+            NodeKind::NodeGoto => {
+                let label = node.val.unwrap();
+                self.gen_jump(
+                    &format!(".L.label.{}.{}", self.function_name, label),
+                    &node.var,
+                    &format!(".L.depth.{}.{}", self.function_name, label),
+                );
+            }
+            NodeKind::NodeLabel => {
+                let label = node.val.unwrap();
+                println!(
+                    ".set .L.depth.{}.{}, {}",
+                    self.function_name,
+                    label,
+                    self.depth * 8
+                );
+                println!(".L.label.{}.{}:", self.function_name, label);
+                self.gen_stmt(*node.lhs.unwrap());
+            }
+            NodeKind::NodeVla => {
+                // the stack pointer is saved above the array's address, for
+                // leaving its scope
+                let var = node.var.unwrap();
+                self.vla_depths.insert(var.offset, self.depth);
+                println!("  mov [rbp - {}], rsp", var.offset - 8);
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_alloca();
+                println!("  mov [rbp - {}], rax", var.offset);
+            }
+            NodeKind::NodeMemZero => {
+                let var = node.var.unwrap();
+                println!("  mov rcx, {}", var.ty.size);
+                println!("  lea rdi, [rbp - {}]", var.offset);
+                println!("  mov al, 0");
+                println!("  rep stosb");
+            }
+            NodeKind::NodeReturn => {
+                if let Some(lhs) = node.lhs {
+                    let ty = lhs.ty.clone().unwrap();
+                    self.gen_expr(*lhs);
+                    self.pop("rax");
+                    if ty.is_flonum() {
+                        println!("  movq xmm0, rax");
+                    } else if ty.is_struct() {
+                        self.gen_return_struct(&ty);
+                    }
+                }
+                self.gen_epilogue();
+            }
+            _ => {
+                // expression statement: its value is left in rax, which is
+                // what falling off the end of the program returns
+                self.gen_expr(node);
+                self.pop("rax");
+            }
+        }
+
+        self.check_depth(&kind);
+    }
+
+    // Push the value of an expression.
+    fn gen_expr(&mut self, node: Node) {
         match node.kind {
             NodeKind::NodeNum => {
-                println!("  push {}", node.val.unwrap());
+                let ty = node.ty.unwrap();
+                if ty.is_flonum() {
+                    let bits = match ty.kind {
+                        TypeKind::TyFloat => (node.fval as f32).to_bits() as u64,
+                        _ => node.fval.to_bits(),
+                    };
+                    println!("  mov rax, {}", bits);
+                    self.push("rax");
+                } else if node.ival == node.ival as i32 as i64 {
+                    self.push(&node.ival.to_string());
+                } else {
+                    // push only takes 32-bit immediates
+                    println!("  mov rax, {}", node.ival);
+                    self.push("rax");
+                }
                 return;
             }
             NodeKind::NodeLVar => {
+                let ty = node.ty.clone().unwrap();
                 self.gen_lval(node);
-                println!("  pop rax");
-                println!("  mov rax, [rax]");
-                println!("  push rax");
+                self.pop("rax");
+                CodeGen::load(&ty);
+                self.push("rax");
                 return;
             }
             NodeKind::NodeDeref => {
-                self.gen_stmt(*node.lhs.unwrap());
-                println!("  pop rax");
-                println!("  mov rax, [rax]");
-                println!("  push rax");
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                CodeGen::load(&node.ty.unwrap());
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeMember => {
+                let ty = node.ty.clone().unwrap();
+                let member = node.member.clone().unwrap();
+                self.gen_lval(node);
+                self.pop("rax");
+                if member.is_bitfield {
+                    CodeGen::load_bitfield(&member);
+                } else {
+                    CodeGen::load(&ty);
+                }
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeCast => {
+                let lhs = *node.lhs.unwrap();
+                let from = lhs.ty.clone().unwrap();
+                self.gen_expr(lhs);
+                self.pop("rax");
+                self.gen_cast(&from, &node.ty.unwrap());
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeFuncall => {
+                self.gen_funcall(node);
+                return;
+            }
+            NodeKind::NodeVaStart => {
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_va_start();
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeAlloca => {
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_alloca();
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeVaArg => {
+                let ty = node.ty.unwrap();
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_va_arg(&ty);
+                CodeGen::load(&ty);
+                self.push("rax");
+                return;
+            }
+            NodeKind::NodeOldValue => {
+                // pushed by the enclosing NodeAssignOp
                 return;
             }
             NodeKind::NodeAddr => {
@@ -55,80 +852,127 @@ impl CodeGen {
                 return;
             }
             NodeKind::NodeAssign => {
+                let ty = node.ty.unwrap();
+                let bitfield = CodeGen::bitfield_of(node.lhs.as_ref().unwrap());
                 self.gen_lval(*node.lhs.unwrap());
-                self.gen_stmt(*node.rhs.unwrap());
-                println!("  pop rdi");
-                println!("  pop rax");
-                println!("  mov [rax], rdi");
-                println!("  push rdi");
+                self.gen_expr(*node.rhs.unwrap());
+                self.pop("rdi");
+                self.pop("rax");
+                match bitfield {
+                    Some(member) => CodeGen::store_bitfield(&member),
+                    None => CodeGen::store(&ty),
+                }
+                self.push("rdi");
                 return;
             }
-            NodeKind::NodeBlock => {
-                for stmt in node.body.into_iter() {
-                    self.gen_stmt(*stmt.unwrap());
-                    println!("  pop rax");
+            NodeKind::NodeAssignOp => {
+                // keep the address on the stack and load the current value
+                // under it, so the lvalue is evaluated only once
+                let ty = node.ty.unwrap();
+                let bitfield = CodeGen::bitfield_of(node.lhs.as_ref().unwrap());
+                self.gen_lval(*node.lhs.unwrap());
+                self.push("[rsp]");
+                self.pop("rax");
+                match bitfield {
+                    Some(ref member) => CodeGen::load_bitfield(member),
+                    None => CodeGen::load(&ty),
                 }
-                println!("  push rax");
+                self.push("rax");
+                self.gen_expr(*node.rhs.unwrap());
+                self.pop("rdi");
+                self.pop("rax");
+                match bitfield {
+                    Some(member) => CodeGen::store_bitfield(&member),
+                    None => CodeGen::store(&ty),
+                }
+                self.push("rdi");
                 return;
             }
-            NodeKind::NodeIf => {
-                self.gen_stmt(*node.cond.unwrap());
-                println!("  pop rax");
-                println!("  cmp rax, 0");
-                println!("  je .L.else.{}", self.jmp_counter);
-                self.gen_stmt(*node.then.unwrap());
-                println!("  jmp .L.end.{}", self.jmp_counter);
-                println!(".L.else.{}:", self.jmp_counter);
-                if let Some(els) = node.els {
-                    self.gen_stmt(*els);
-                }
-                println!(".L.end.{}:", self.jmp_counter);
-                self.jmp_counter += 1;
+            NodeKind::NodeBitNot => {
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                println!("  not rax");
+                CodeGen::truncate(&node.ty.unwrap());
+                self.push("rax");
                 return;
             }
-            NodeKind::NodeFor => {
-                if let Some(init) = node.init {
-                    self.gen_stmt(*init);
-                }
-                println!(".L.begin.{}:", self.jmp_counter);
-                if let Some(cond) = node.cond {
-                    self.gen_stmt(*cond);
-                    println!("  cmp rax, 0");
-                    println!("  je .L.end.{}", self.jmp_counter);
-                }
-                self.gen_stmt(*node.then.unwrap());
-                if let Some(inc) = node.inc {
-                    self.gen_stmt(*inc);
-                }
-                println!("  jmp .L.begin.{}", self.jmp_counter);
-                println!(".L.end.{}:", self.jmp_counter);
-                self.jmp_counter += 1;
+            NodeKind::NodeCond => {
+                let label = self.new_label();
+                self.gen_branch_if_zero(*node.cond.unwrap(), &format!(".L.else.{}", label));
+                self.gen_expr(*node.then.unwrap());
+                println!("  jmp .L.end.{}", label);
+                println!(".L.else.{}:", label);
+                // only one of the branches runs
+                self.depth -= 1;
+                self.gen_expr(*node.els.unwrap());
+                println!(".L.end.{}:", label);
                 return;
             }
-            NodeKind::NodeReturn => {
-                self.gen_stmt(*node.lhs.unwrap());
-                println!("  pop rax");
-                println!("  mov rsp, rbp");
-                println!("  pop rbp");
-                println!("  ret");
+            NodeKind::NodeComma | NodeKind::NodeCompoundLiteral => {
+                self.gen_expr(*node.lhs.unwrap());
+                self.pop("rax");
+                self.gen_expr(*node.rhs.unwrap());
+                return;
+            }
+            NodeKind::NodeStmtExpr => {
+                // the last expression statement leaves its value in rax
+                let outer = self.stmt_depth;
+                self.stmt_depth = self.depth;
+                for stmt in node.body.into_iter() {
+                    self.gen_stmt(*stmt.unwrap());
+                }
+                // freeing its variable-length arrays keeps rax
+                CodeGen::free_vlas(&node.var);
+                self.stmt_depth = outer;
+                self.push("rax");
                 return;
             }
             _ => {}
         }
 
-        self.gen_stmt(*node.lhs.unwrap());
-        self.gen_stmt(*node.rhs.unwrap());
-
-        println!("  pop rdi");
-        println!("  pop rax");
+        let ty = node.ty.unwrap();
+        // comparisons are unsigned if their converted operands are
+        let lhs_ty = node.lhs.as_ref().unwrap().ty.clone().unwrap();
+        self.gen_expr(*node.lhs.unwrap());
+        self.gen_expr(*node.rhs.unwrap());
 
+        self.pop("rdi");
+        self.pop("rax");
+        if lhs_ty.is_flonum() {
+            CodeGen::gen_flonum_binary(&node.kind, &lhs_ty);
+            self.push("rax");
+            return;
+        }
         match node.kind {
             NodeKind::NodeAdd => println!("  add rax, rdi"),
             NodeKind::NodeSub => println!("  sub rax, rdi"),
             NodeKind::NodeMul => println!(" imul rax, rdi"),
-            NodeKind::NodeDiv => {
-                println!("  cqo");
-                println!("  idiv rdi");
+            NodeKind::NodeDiv | NodeKind::NodeMod => {
+                if ty.is_unsigned {
+                    println!("  xor edx, edx");
+                    println!("  div rdi");
+                } else {
+                    println!("  cqo");
+                    println!("  idiv rdi");
+                }
+                if node.kind == NodeKind::NodeMod {
+                    println!("  mov rax, rdx");
+                }
+            }
+            NodeKind::NodeBitAnd => println!("  and rax, rdi"),
+            NodeKind::NodeBitOr => println!("  or rax, rdi"),
+            NodeKind::NodeBitXor => println!("  xor rax, rdi"),
+            NodeKind::NodeShl => {
+                println!("  mov rcx, rdi");
+                println!("  shl rax, cl");
+            }
+            NodeKind::NodeShr => {
+                println!("  mov rcx, rdi");
+                if ty.is_unsigned {
+                    println!("  shr rax, cl");
+                } else {
+                    println!("  sar rax, cl");
+                }
             }
             NodeKind::NodeEQ => {
                 println!("  cmp rax, rdi");
@@ -142,56 +986,444 @@ impl CodeGen {
             }
             NodeKind::NodeLT => {
                 println!("  cmp rax, rdi");
-                println!("  setl al");
+                if lhs_ty.is_unsigned {
+                    println!("  setb al");
+                } else {
+                    println!("  setl al");
+                }
                 println!("  movzb rax, al");
             }
             NodeKind::NodeLE => {
                 println!("  cmp rax, rdi");
-                println!("  setle al");
+                if lhs_ty.is_unsigned {
+                    println!("  setbe al");
+                } else {
+                    println!("  setle al");
+                }
                 println!("  movzb rax, al");
             }
             _ => eprintln!("Unsupported token kind!"),
         }
 
-        println!("  push rax");
+        // wrap the result around to its type
+        CodeGen::truncate(&ty);
+        self.push("rax");
+    }
+
+    // Compute `rax op rdi` on floating-point operands of type `ty`. A NaN
+    // operand makes every comparison false, except "!=".
+    fn gen_flonum_binary(kind: &NodeKind, ty: &Type) {
+        let suffix = CodeGen::sse_suffix(ty);
+        println!("  movq xmm0, rax");
+        println!("  movq xmm1, rdi");
+        match kind {
+            NodeKind::NodeAdd => println!("  add{} xmm0, xmm1", suffix),
+            NodeKind::NodeSub => println!("  sub{} xmm0, xmm1", suffix),
+            NodeKind::NodeMul => println!("  mul{} xmm0, xmm1", suffix),
+            NodeKind::NodeDiv => println!("  div{} xmm0, xmm1", suffix),
+            NodeKind::NodeEQ => {
+                println!("  ucomi{} xmm0, xmm1", suffix);
+                println!("  sete al");
+                println!("  setnp dl");
+                println!("  and al, dl");
+            }
+            NodeKind::NodeNE => {
+                println!("  ucomi{} xmm0, xmm1", suffix);
+                println!("  setne al");
+                println!("  setp dl");
+                println!("  or al, dl");
+            }
+            // "above" is false for unordered operands, unlike "below"
+            NodeKind::NodeLT => {
+                println!("  ucomi{} xmm1, xmm0", suffix);
+                println!("  seta al");
+            }
+            NodeKind::NodeLE => {
+                println!("  ucomi{} xmm1, xmm0", suffix);
+                println!("  setae al");
+            }
+            _ => eprintln!("Unsupported token kind!"),
+        }
+
+        match kind {
+            NodeKind::NodeEQ | NodeKind::NodeNE | NodeKind::NodeLT | NodeKind::NodeLE => {
+                println!("  movzb rax, al");
+            }
+            _ => CodeGen::from_xmm0(ty),
+        }
+    }
+
+    // Call a function following the SysV calling convention: the first six
+    // integer arguments go in registers, as do the first eight floating-point
+    // ones in xmm0-xmm7, and the rest are passed on the stack. A struct of at
+    // most 16 bytes is split into eightbytes, each passed like a long or a
+    // double, if there are registers left for all of them. A struct
+    // returned in memory is written to a buffer whose address is passed as
+    // a hidden first argument.
+    fn gen_funcall(&mut self, node: Node) {
+        let ty = node.ty.clone().unwrap();
+        let ret_in_memory = ty.is_struct() && ty.eightbytes().is_none();
+        let mut reg_args = Vec::new();
+        let mut stack_args = Vec::new();
+        let (mut gp, mut fp) = (ret_in_memory as usize, 0);
+        for arg in node.args.into_iter() {
+            let arg = *arg.unwrap();
+            match CodeGen::reg_count(arg.ty.as_ref().unwrap()) {
+                Some((arg_gp, arg_fp))
+                    if gp + arg_gp <= ARG_REGS64.len() && fp + arg_fp <= FP_ARG_REGS =>
+                {
+                    gp += arg_gp;
+                    fp += arg_fp;
+                    reg_args.push(arg);
+                }
+                _ => stack_args.push(arg),
+            }
+        }
+
+        // rsp must be a multiple of 16 at the call, with the stack arguments
+        // pushed. The frame is a multiple of 16 bytes, so only pushed values
+        // count. A struct takes up as many 8-byte slots as it needs.
+        let mut stack_size: i64 = stack_args
+            .iter()
+            .map(|arg| arg.ty.as_ref().unwrap().size.div_ceil(8) as i64)
+            .sum();
+        if (self.depth + stack_size) % 2 == 1 {
+            println!("  sub rsp, 8");
+            self.depth += 1;
+            stack_size += 1;
+        }
+
+        // arguments are pushed right to left, so that the stack ones end up
+        // in order and the register ones pop off in order
+        for arg in stack_args.into_iter().rev() {
+            let arg_ty = arg.ty.clone().unwrap();
+            self.gen_expr(arg);
+            if arg_ty.is_struct() {
+                // copied into its slots, from the address pushed
+                self.pop("rdi");
+                let slots = arg_ty.size.div_ceil(8) as i64;
+                println!("  sub rsp, {}", slots * 8);
+                self.depth += slots;
+                println!("  mov rax, rsp");
+                CodeGen::store(&arg_ty);
+            }
+        }
+        let arg_tys: Vec<Type> = reg_args.iter().map(|arg| arg.ty.clone().unwrap()).collect();
+        for arg in reg_args.into_iter().rev() {
+            self.gen_expr(arg);
+        }
+        // the callee of an indirect call is evaluated last, so that it
+        // comes off the stack first
+        let callee = node.lhs.map(|callee| {
+            self.gen_expr(*callee);
+            self.pop("r10");
+            "r10"
+        });
+        let (mut gp, mut fp) = (ret_in_memory as usize, 0);
+        for arg_ty in arg_tys {
+            if arg_ty.is_struct() {
+                self.pop("r11");
+                CodeGen::load_struct(&arg_ty, &ARG_REGS64, &ARG_REGS8, &mut gp, &mut fp);
+            } else if arg_ty.is_flonum() {
+                self.pop("rax");
+                println!("  movq xmm{}, rax", fp);
+                fp += 1;
+            } else {
+                self.pop(ARG_REGS64[gp]);
+                gp += 1;
+            }
+        }
+        if ret_in_memory {
+            println!("  lea rdi, [rbp - {}]", node.var.as_ref().unwrap().offset);
+        }
+
+        // a variadic callee learns from al how many xmm registers hold
+        // arguments; any other callee ignores it
+        println!("  mov eax, {}", fp);
+        match callee {
+            Some(reg) => println!("  call {}", reg),
+            None => println!("  call {}", node.val.unwrap()),
+        }
+        if stack_size > 0 {
+            println!("  add rsp, {}", stack_size * 8);
+            self.depth -= stack_size;
+        }
+
+        // the upper bits of a return value narrower than its register are
+        // unspecified. A struct returned in memory is at the address the
+        // callee returns in rax, and one returned in registers is stored to
+        // the same buffer.
+        if ty.is_struct() {
+            if !ret_in_memory {
+                println!("  lea r11, [rbp - {}]", node.var.unwrap().offset);
+                let (mut gp, mut fp) = (0, 0);
+                CodeGen::store_struct(&ty, &RET_REGS64, &RET_REGS8, &mut gp, &mut fp);
+                println!("  mov rax, r11");
+            }
+        } else if ty.is_flonum() {
+            CodeGen::from_xmm0(&ty);
+        } else {
+            CodeGen::truncate(&ty);
+        }
+        self.push("rax");
+    }
+
+    // Return the struct at the address in rax: in rax and rdx, or xmm0 and
+    // xmm1, if it fits, and otherwise copied to the caller's buffer, whose
+    // address is returned in rax.
+    fn gen_return_struct(&mut self, ty: &Type) {
+        match self.ret_ptr_offset {
+            Some(offset) => {
+                println!("  mov rdi, rax");
+                println!("  mov rax, [rbp - {}]", offset);
+                CodeGen::store(ty);
+            }
+            None => {
+                println!("  mov r11, rax");
+                let (mut gp, mut fp) = (0, 0);
+                CodeGen::load_struct(ty, &RET_REGS64, &RET_REGS8, &mut gp, &mut fp);
+            }
+        }
+    }
+
+    // Initialize the va_list at rax. Its members are the offsets of the next
+    // integer and floating-point arguments in the register save area, the
+    // address of the next argument passed on the stack, and the address of
+    // the register save area.
+    fn gen_va_start(&mut self) {
+        let (gp, fp, stack) = self.named_args;
+        println!("  mov dword ptr [rax], {}", gp * 8);
+        println!(
+            "  mov dword ptr [rax + 4], {}",
+            ARG_REGS64.len() * 8 + fp * 16
+        );
+        let args = self.stack_args_base();
+        println!("  lea rdi, [{} + {}]", args, 16 + stack * 8);
+        println!("  mov [rax + 8], rdi");
+        println!("  lea rdi, [rbp - {}]", self.va_area_offset);
+        println!("  mov [rax + 16], rdi");
+    }
+
+    // Set rax to the address of the next argument of type `ty` in the
+    // va_list at rax, and advance the va_list past it. The argument is in the
+    // register save area until the registers of its class run out, and on
+    // the stack after that.
+    fn gen_va_arg(&mut self, ty: &Type) {
+        let label = self.new_label();
+        let (offset, limit, step) = if ty.is_flonum() {
+            ("dword ptr [rax + 4]", VA_AREA_SIZE, 16)
+        } else {
+            ("dword ptr [rax]", ARG_REGS64.len() * 8, 8)
+        };
+        println!("  cmp {}, {}", offset, limit);
+        println!("  jae .L.va_stack.{}", label);
+        println!("  mov edi, {}", offset);
+        println!("  add rdi, [rax + 16]");
+        println!("  add {}, {}", offset, step);
+        println!("  jmp .L.end.{}", label);
+        println!(".L.va_stack.{}:", label);
+        println!("  mov rdi, [rax + 8]");
+        println!("  lea rdx, [rdi + 8]");
+        println!("  mov [rax + 8], rdx");
+        println!(".L.end.{}:", label);
+        println!("  mov rax, rdi");
     }
 
     pub fn generate(&mut self, parser: &Parser) {
         println!(".intel_syntax noprefix");
 
-        let stack_size = CodeGen::calculate_total_offsets(parser);
+        self.extern_functions = parser
+            .functions
+            .iter()
+            .filter(|function| function.body.is_none())
+            .map(|function| function.name.clone())
+            .collect();
+        for var in parser.globals.iter() {
+            CodeGen::gen_global(var);
+        }
+        println!("  .text");
+        for function in parser.functions.iter() {
+            if function.body.is_some() {
+                self.gen_function(function.clone());
+            }
+        }
+    }
+
+    // Emit a global variable: its initial contents in .data, or its size in
+    // .bss if it is all zero.
+    fn gen_global(var: &GlobalVar) {
+        // another object file defines it
+        if !var.is_definition {
+            return;
+        }
+        if var.is_static {
+            println!(".local {}", var.name);
+        } else {
+            println!(".global {}", var.name);
+        }
+        let data = match var.init_data {
+            Some(ref data) => data,
+            None => {
+                println!("  .bss");
+                println!("  .align {}", var.ty.align);
+                println!("{}:", var.name);
+                println!("  .zero {}", var.ty.size);
+                return;
+            }
+        };
 
-        println!(".global main");
-        println!("main:");
+        // a const object cannot be written, unless the dynamic linker has to
+        // fill in an address in it
+        if var.ty.is_readonly() && var.relocations.is_empty() {
+            println!("  .section .rodata");
+        } else {
+            println!("  .data");
+        }
+        println!("  .align {}", var.ty.align);
+        println!("{}:", var.name);
+        let mut relocations = var.relocations.iter().peekable();
+        let mut offset = 0;
+        while offset < data.len() {
+            match relocations.peek() {
+                Some(reloc) if reloc.offset == offset => {
+                    println!("  .quad {}{:+}", reloc.label, reloc.addend);
+                    relocations.next();
+                    offset += 8;
+                }
+                _ => {
+                    println!("  .byte {}", data[offset]);
+                    offset += 1;
+                }
+            }
+        }
+    }
+
+    fn gen_function(&mut self, function: Function) {
+        let stack_size = align_to(function.stack_size, 16);
+        self.function_name = function.name.clone();
+
+        if function.is_static {
+            println!(".local {}", function.name);
+        } else {
+            println!(".global {}", function.name);
+        }
+        println!("{}:", function.name);
 
-        // prologue: allocate the space of 26 variables
+        // prologue: allocate the space of the local variables. The stack is
+        // only 16-byte aligned, so a frame with a local aligned beyond that
+        // gets a second frame below the first, at an address aligned enough,
+        // whose saved rbp is the first frame's.
         println!("  push rbp");
         println!("  mov rbp, rsp");
+        self.realigned = function.stack_align > 16;
+        if self.realigned {
+            println!("  and rsp, -{}", function.stack_align);
+            println!("  sub rsp, {}", function.stack_align - 8);
+            println!("  push rbp");
+            println!("  mov rbp, rsp");
+        }
         println!("  sub rsp, {}", stack_size);
 
-        let code = parser.code.clone();
+        // a variadic function saves all of the argument registers, for
+        // va_arg to find the unnamed arguments in
+        if let Some(ref va_area) = function.va_area {
+            for (i, reg) in ARG_REGS64.iter().enumerate() {
+                println!("  mov [rbp - {}], {}", va_area.offset - i * 8, reg);
+            }
+            for i in 0..FP_ARG_REGS {
+                let offset = va_area.offset - ARG_REGS64.len() * 8 - i * 16;
+                println!("  movsd qword ptr [rbp - {}], xmm{}", offset, i);
+            }
+            self.va_area_offset = va_area.offset;
+        }
 
-        for tree in code {
-            if tree.is_none() {
-                break;
+        // the address to return a struct to comes first
+        self.ret_ptr_offset = function.ret_ptr.as_ref().map(|var| var.offset);
+        let mut gp = 0;
+        if let Some(offset) = self.ret_ptr_offset {
+            println!("  mov [rbp - {}], rdi", offset);
+            gp += 1;
+        }
+
+        // store the arguments to the slots of their parameters, the ones in
+        // registers first, before copying the others can overwrite them
+        let (mut fp, mut stack) = (0, 0);
+        let mut stack_params = Vec::new();
+        for param in function.params.iter() {
+            let ty = &param.ty;
+            match CodeGen::reg_count(ty) {
+                Some((arg_gp, arg_fp))
+                    if gp + arg_gp <= ARG_REGS64.len() && fp + arg_fp <= FP_ARG_REGS =>
+                {
+                    if ty.is_struct() {
+                        println!("  lea r11, [rbp - {}]", param.offset);
+                        CodeGen::store_struct(ty, &ARG_REGS64, &ARG_REGS8, &mut gp, &mut fp);
+                    } else if ty.is_flonum() {
+                        let ptr = if ty.size == 4 { "dword" } else { "qword" };
+                        let suffix = CodeGen::sse_suffix(ty);
+                        println!(
+                            "  mov{} {} ptr [rbp - {}], xmm{}",
+                            suffix, ptr, param.offset, fp
+                        );
+                        fp += 1;
+                    } else {
+                        let reg = match ty.size {
+                            1 => ARG_REGS8[gp],
+                            2 => ARG_REGS16[gp],
+                            4 => ARG_REGS32[gp],
+                            _ => ARG_REGS64[gp],
+                        };
+                        println!("  mov [rbp - {}], {}", param.offset, reg);
+                        gp += 1;
+                    }
+                }
+                _ => {
+                    // the caller pushed it above the return address
+                    stack_params.push((param, 16 + stack * 8));
+                    stack += ty.size.div_ceil(8);
+                }
             }
-            self.gen_stmt(*tree.unwrap());
-            println!("  pop rax");
         }
+        let args = if stack_params.is_empty() {
+            "rbp"
+        } else {
+            self.stack_args_base()
+        };
+        for (param, arg_offset) in stack_params {
+            if param.ty.is_struct() {
+                println!("  lea rdi, [{} + {}]", args, arg_offset);
+            } else {
+                println!("  mov rdi, [{} + {}]", args, arg_offset);
+            }
+            println!("  lea rax, [rbp - {}]", param.offset);
+            CodeGen::store(&param.ty);
+        }
+        self.named_args = (gp, fp, stack);
+
+        self.gen_stmt(*function.body.unwrap());
 
         // epilogue: return the value of the last expression at RAX
+        self.gen_epilogue();
+    }
+
+    // Leave the frame, or both frames of a realigned one, and return.
+    fn gen_epilogue(&self) {
+        if self.realigned {
+            println!("  mov rsp, rbp");
+            println!("  pop rbp");
+        }
         println!("  mov rsp, rbp");
         println!("  pop rbp");
         println!("  ret");
     }
 
-    // Round up `n` to the nearest multiple of `align`
-    fn align_to(n: usize, align: usize) -> usize {
-        (n + align - 1) / align * align
-    }
-
-    fn calculate_total_offsets(parser: &Parser) -> usize {
-        let offset = parser.locals.len() * 8;
-        CodeGen::align_to(offset, 16)
+    // The register the arguments passed on the stack are above: rbp, or
+    // in a realigned frame the first frame's rbp, which is loaded to r10.
+    fn stack_args_base(&self) -> &'static str {
+        if !self.realigned {
+            return "rbp";
+        }
+        println!("  mov r10, [rbp]");
+        "r10"
     }
 }
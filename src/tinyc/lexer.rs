@@ -9,86 +9,82 @@ enum TokenKind {
     TkIdent,
     TkKeyword,
     TkNum,
+    TkStr,
     TkEOF,
 }
 
 type TokenLink = Option<Rc<RefCell<Token>>>;
 
-type PeekableString<'a> = Peekable<std::str::Chars<'a>>;
+// Every punctuator the tokenizer knows. They are read greedily one character
+// at a time, so the order of this list does not matter.
+const PUNCTUATORS: &[&str] = &[
+    "<<=", ">>=", "==", "!=", "<=", ">=", "<<", ">>", "+=", "-=", "*=", "/=", "%=", "&=", "|=",
+    "^=", "++", "--", "+", "-", "*", "/", "%", "(", ")", ";", "{", "}", "&", "|", "^", "~", "=",
+    "<", ">", "?", ":", ",", ".", "->", "[", "]", "...",
+];
+
+// An integer constant, with what its spelling says about its type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntLiteral {
+    pub val: u64,
+    pub is_decimal: bool,
+    pub is_unsigned: bool, // has a "u" suffix
+    pub longs: usize,      // number of "l"s in the suffix
+}
 
 #[derive(Debug, Clone)]
 struct Token {
     kind: TokenKind,
     next: TokenLink,
-    val: Option<String>,
-    string: String, // token string
+    val: Option<IntLiteral>,
+    fval: Option<f64>,        // value of a floating constant
+    str_val: Option<Vec<u8>>, // contents of a string literal, without the terminating NUL
+    string: String,           // token string
+    loc: usize,               // byte offset of the token in the input
 }
 
 #[derive(Debug, Clone)]
 pub struct Tokenizer<'a> {
     current: TokenLink,
+    input: &'a str,
     chars: Peekable<std::str::Chars<'a>>,
+    pos: usize,
     head: TokenLink,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn tokenize(string: &'a str) -> Self {
-        let mut tokenizer = Tokenizer::new_empty(string.chars().peekable());
-        let mut next_char: Option<&char>;
+        let mut tokenizer = Tokenizer::new_empty(string);
+        let mut next_char: Option<char>;
         loop {
-            next_char = tokenizer.chars.peek();
+            let loc = tokenizer.pos;
+            next_char = tokenizer.chars.peek().copied();
             match next_char {
                 Some(' ') => {
-                    tokenizer.chars.next();
-                }
-                // is there a better notation?
-                Some('+') | Some('-') | Some('*') | Some('/') | Some('(') | Some(')')
-                | Some(';') | Some('{') | Some('}') | Some('&') => {
-                    let string = tokenizer.chars.next().unwrap().to_string();
-                    tokenizer.new_token(TokenKind::TkReserved, string);
-                }
-                Some('=') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
-                    let next_char = tokenizer.chars.peek();
-                    match next_char {
-                        Some(c) if c == &'=' => string.push(tokenizer.chars.next().unwrap()),
-                        _ => {}
-                    }
-                    tokenizer.new_token(TokenKind::TkReserved, string);
+                    tokenizer.next_char();
                 }
-                Some('>') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
-                    string = Tokenizer::peek_and_append_char(&mut tokenizer, string, '=');
-                    tokenizer.new_token(TokenKind::TkReserved, string);
+                Some('.') if tokenizer.float_literal_len().is_some() => {
+                    tokenizer.new_token(TokenKind::TkNum, String::from(""), loc);
                 }
-                Some('<') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
-                    string = Tokenizer::peek_and_append_char(&mut tokenizer, string, '=');
-                    tokenizer.new_token(TokenKind::TkReserved, string);
-                }
-                Some('!') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
-                    let next_char = tokenizer.chars.next().unwrap_or('\0');
-                    if next_char != '=' {
-                        eprintln!("tokenizer: unexpected token '!'");
-                        process::exit(1);
-                    } else {
-                        string.push(next_char);
-                    }
-                    tokenizer.new_token(TokenKind::TkReserved, string);
+                Some(c) if PUNCTUATORS.iter().any(|p| p.starts_with(c)) => {
+                    let string = Tokenizer::parse_punct(&mut tokenizer);
+                    tokenizer.new_token(TokenKind::TkReserved, string, loc);
                 }
                 Some('a'..='z') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
+                    let mut string = tokenizer.next_char().unwrap().to_string();
                     string = Tokenizer::parse_ident(&mut tokenizer, string);
-                    tokenizer.new_token(TokenKind::TkIdent, string);
+                    tokenizer.new_token(TokenKind::TkIdent, string, loc);
                 }
                 Some('_') => {
-                    let mut string = tokenizer.chars.next().unwrap().to_string();
+                    let mut string = tokenizer.next_char().unwrap().to_string();
                     string = Tokenizer::parse_ident(&mut tokenizer, string);
-                    tokenizer.new_token(TokenKind::TkIdent, string);
+                    tokenizer.new_token(TokenKind::TkIdent, string, loc);
                 }
                 Some('0'..='9') => {
-                    tokenizer.new_token(TokenKind::TkNum, String::from(""));
+                    tokenizer.new_token(TokenKind::TkNum, String::from(""), loc);
+                }
+                Some('"') => {
+                    tokenizer.new_token(TokenKind::TkStr, String::from(""), loc);
                 }
                 Some(_) => {
                     eprintln!("{}", string);
@@ -96,7 +92,7 @@ impl<'a> Tokenizer<'a> {
                     process::exit(1);
                 }
                 None => {
-                    tokenizer.new_token(TokenKind::TkEOF, String::from(""));
+                    tokenizer.new_token(TokenKind::TkEOF, String::from(""), loc);
                     break;
                 }
             }
@@ -105,27 +101,48 @@ impl<'a> Tokenizer<'a> {
         tokenizer
     }
 
-    fn new_empty(chars: PeekableString<'a>) -> Tokenizer {
+    fn new_empty(input: &'a str) -> Tokenizer<'a> {
         Tokenizer {
             current: None,
-            chars: chars,
+            input,
+            chars: input.chars().peekable(),
+            pos: 0,
             head: None,
         }
     }
 
-    fn new_token(&mut self, mut kind: TokenKind, string: String) {
-        let mut val: Option<String> = None;
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn new_token(&mut self, mut kind: TokenKind, mut string: String, loc: usize) {
+        let mut val: Option<IntLiteral> = None;
+        let mut fval: Option<f64> = None;
+        let mut str_val: Option<Vec<u8>> = None;
         if kind == TokenKind::TkNum {
-            val = self.parse_int();
+            match self.float_literal_len() {
+                Some(len) => {
+                    string = (0..len).filter_map(|_| self.next_char()).collect();
+                    fval = Some(self.parse_float(&string, loc));
+                }
+                None => val = Some(self.parse_int(loc)),
+            }
+        } else if kind == TokenKind::TkStr {
+            str_val = Some(self.parse_string(loc));
         } else if kind == TokenKind::TkIdent {
             kind = self.convert_keyword(&string)
         }
 
         let token = Token {
-            kind: kind,
+            kind,
             next: None,
-            val: val,
-            string: string,
+            val,
+            fval,
+            str_val,
+            string,
+            loc,
         };
         let token_pointer = Rc::new(RefCell::new(token));
 
@@ -141,6 +158,12 @@ impl<'a> Tokenizer<'a> {
         self.current = Some(token_pointer);
     }
 
+    fn advance(&mut self) {
+        if let Some(head) = self.head.take() {
+            self.head = head.borrow().next.clone();
+        }
+    }
+
     pub fn expect(&mut self, op: &str) {
         if let Some(head) = self.head.as_ref() {
             let head_ref = head.borrow();
@@ -153,11 +176,7 @@ impl<'a> Tokenizer<'a> {
             process::exit(1);
         }
 
-        self.head.take().map(|head| {
-            if let Some(next) = head.borrow().next.clone() {
-                self.head = Some(next);
-            }
-        });
+        self.advance();
     }
 
     pub fn consume(&mut self, op: &str) -> bool {
@@ -166,7 +185,7 @@ impl<'a> Tokenizer<'a> {
             if head_ref.kind != TokenKind::TkReserved && head_ref.kind != TokenKind::TkKeyword {
                 return false;
             }
-            if head_ref.string != op.to_string() {
+            if head_ref.string != op {
                 return false;
             }
         } else {
@@ -174,29 +193,48 @@ impl<'a> Tokenizer<'a> {
             process::exit(1);
         }
 
-        self.head.take().map(|head| {
-            if let Some(next) = head.borrow().next.clone() {
-                self.head = Some(next);
+        self.advance();
+        true
+    }
+
+    // Whether the next token is `op`, without consuming it.
+    pub fn peek(&self, op: &str) -> bool {
+        match self.head {
+            Some(ref head) => {
+                let head_ref = head.borrow();
+                (head_ref.kind == TokenKind::TkReserved || head_ref.kind == TokenKind::TkKeyword)
+                    && head_ref.string == op
             }
-        });
-        return true;
+            None => false,
+        }
     }
 
-    pub fn expect_number(&mut self) -> Option<String> {
-        let val = self.head.take().map(|head| {
-            let mut head_ref = head.borrow_mut();
-            if let Some(next) = head_ref.next.take() {
-                if head_ref.kind != TokenKind::TkNum {
-                    eprintln!("Not a number");
-                    process::exit(1);
-                }
-                self.head = Some(next);
-                head_ref.val.clone()
-            } else {
-                None
+    pub fn expect_number(&mut self) -> IntLiteral {
+        let val = match self.head {
+            Some(ref head) => head.borrow().val,
+            None => None,
+        };
+        match val {
+            Some(val) => {
+                self.advance();
+                val
+            }
+            None => {
+                self.report_at(self.loc(), "expected a number");
+                process::exit(1);
             }
-        });
-        return val.unwrap_or(Some(String::from("")));
+        }
+    }
+
+    // Consume a floating constant and return its value, and whether it has
+    // the "f" suffix that makes it a float rather than a double.
+    pub fn is_float_token(&mut self) -> Option<(f64, bool)> {
+        let head = self.head.clone()?;
+        let head_ref = head.borrow();
+        let val = head_ref.fval?;
+        let is_float = head_ref.string.ends_with(['f', 'F']);
+        self.advance();
+        Some((val, is_float))
     }
 
     pub fn is_ident_token(&mut self) -> Option<String> {
@@ -206,11 +244,7 @@ impl<'a> Tokenizer<'a> {
             let head_ref = head.borrow();
             if head_ref.kind == TokenKind::TkIdent {
                 string = Some(head_ref.string.clone());
-                self.head.take().map(|head| {
-                    if let Some(next) = head.borrow().next.clone() {
-                        self.head = Some(next);
-                    }
-                });
+                self.advance();
             } else {
                 string = None;
             }
@@ -222,6 +256,72 @@ impl<'a> Tokenizer<'a> {
         string
     }
 
+    // The identifier at the head, without consuming it.
+    pub fn peek_ident(&self) -> Option<String> {
+        let head_ref = self.head.as_ref()?.borrow();
+        if head_ref.kind != TokenKind::TkIdent {
+            return None;
+        }
+        Some(head_ref.string.clone())
+    }
+
+    // Consume a string literal and return its contents.
+    pub fn is_string_token(&mut self) -> Option<Vec<u8>> {
+        let head = self.head.clone()?;
+        let val = head.borrow().str_val.clone()?;
+        self.advance();
+        Some(val)
+    }
+
+    // Skip the tokens up to and including `op`, outside of any parentheses,
+    // brackets or braces.
+    pub fn skip_until(&mut self, op: &str) {
+        let mut depth = 0;
+        while !self.at_eof() {
+            if depth == 0 && self.consume(op) {
+                return;
+            }
+            if self.peek("(") || self.peek("[") || self.peek("{") {
+                depth += 1;
+            } else if self.peek(")") || self.peek("]") || self.peek("}") {
+                depth -= 1;
+            }
+            self.advance();
+        }
+    }
+
+    // A label is an identifier followed by ":". Consume both and return the
+    // label name, or leave the tokens untouched and return None.
+    pub fn is_label_token(&mut self) -> Option<String> {
+        let head = self.head.clone()?;
+        let head_ref = head.borrow();
+        if head_ref.kind != TokenKind::TkIdent {
+            return None;
+        }
+        let next = head_ref.next.clone()?;
+        let next_ref = next.borrow();
+        if next_ref.kind != TokenKind::TkReserved || next_ref.string != ":" {
+            return None;
+        }
+
+        self.head = next_ref.next.clone();
+        Some(head_ref.string.clone())
+    }
+
+    // The location of the next token, for diagnostics.
+    pub fn loc(&self) -> usize {
+        match self.head {
+            Some(ref head) => head.borrow().loc,
+            None => self.input.len(),
+        }
+    }
+
+    // Print the input with a caret under `loc`, followed by `msg`.
+    pub fn report_at(&self, loc: usize, msg: &str) {
+        eprintln!("{}", self.input);
+        eprintln!("{}^ {}", " ".repeat(loc), msg);
+    }
+
     pub fn at_eof(&mut self) -> bool {
         if let Some(ref head) = self.head {
             return head.borrow().kind == TokenKind::TkEOF;
@@ -231,70 +331,353 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    fn parse_int(&mut self) -> Option<String> {
-        let mut integer = String::from("");
+    // Read an integer constant: a decimal, octal ("0"), hexadecimal ("0x") or
+    // binary ("0b") number, followed by an optional "u", "l" or "ll" suffix.
+    fn parse_int(&mut self, loc: usize) -> IntLiteral {
+        let rest = &self.input[self.pos..];
+        let lower = rest.get(..2).map(|prefix| prefix.to_ascii_lowercase());
+        let (radix, prefix_len) = match lower.as_deref() {
+            Some("0x") => (16, 2),
+            Some("0b") => (2, 2),
+            _ if rest.starts_with('0') => (8, 0),
+            _ => (10, 0),
+        };
+        for _ in 0..prefix_len {
+            self.next_char();
+        }
 
-        while let Some(next_char) = self.chars.peek() {
-            if next_char.is_numeric() {
-                integer.push(self.chars.next().unwrap())
-            } else if next_char == &' ' {
-                self.chars.next();
-                continue;
-            } else {
+        let mut val: u64 = 0;
+        let mut digits = 0;
+        while let Some(digit) = self.chars.peek().and_then(|c| c.to_digit(radix)) {
+            val = match val
+                .checked_mul(radix as u64)
+                .and_then(|val| val.checked_add(digit as u64))
+            {
+                Some(val) => val,
+                None => {
+                    self.report_at(loc, "integer constant is too large");
+                    process::exit(1);
+                }
+            };
+            digits += 1;
+            self.next_char();
+        }
+        if digits == 0 {
+            self.report_at(loc, "no digits in integer constant");
+            process::exit(1);
+        }
+        if radix == 8 && self.chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.report_at(self.pos, "invalid digit in octal constant");
+            process::exit(1);
+        }
+
+        let suffix_loc = self.pos;
+        let mut suffix = String::new();
+        while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+            suffix.push(*c);
+            self.next_char();
+        }
+        let (is_unsigned, longs) = match suffix.as_str() {
+            "" => (false, 0),
+            "u" | "U" => (true, 0),
+            "l" | "L" => (false, 1),
+            "ll" | "LL" => (false, 2),
+            "ul" | "uL" | "Ul" | "UL" | "lu" | "lU" | "Lu" | "LU" => (true, 1),
+            "ull" | "uLL" | "Ull" | "ULL" | "llu" | "llU" | "LLu" | "LLU" => (true, 2),
+            _ => {
+                self.report_at(
+                    suffix_loc,
+                    &format!("invalid suffix \"{}\" on integer constant", suffix),
+                );
+                process::exit(1);
+            }
+        };
+
+        IntLiteral {
+            val,
+            is_decimal: radix == 10,
+            is_unsigned,
+            longs,
+        }
+    }
+
+    // The length of the floating constant at the current position, or None
+    // if the number there is an integer. A number is read the way the
+    // preprocessor does, as digits, letters, dots and exponent signs.
+    fn float_literal_len(&self) -> Option<usize> {
+        let rest = &self.input[self.pos..];
+        let mut len = 0;
+        let mut prev = ' ';
+        for c in rest.chars() {
+            let is_exponent_sign = (c == '+' || c == '-') && "eEpP".contains(prev);
+            if !c.is_ascii_alphanumeric() && c != '.' && !is_exponent_sign {
                 break;
             }
+            len += 1;
+            prev = c;
+        }
+
+        let number = &rest[..len];
+        let digits = number.strip_prefix('.').unwrap_or(number);
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let is_float = if number.starts_with("0x") || number.starts_with("0X") {
+            number.contains(['.', 'p', 'P'])
+        } else {
+            number.contains(['.', 'e', 'E'])
+        };
+        if is_float {
+            Some(len)
+        } else {
+            None
+        }
+    }
+
+    // Parse a decimal or hexadecimal floating constant. The "f" and "l"
+    // suffixes only select the type, so they are dropped here.
+    fn parse_float(&self, string: &str, loc: usize) -> f64 {
+        let number = string.trim_end_matches(['f', 'F', 'l', 'L']);
+        let val = match number
+            .strip_prefix("0x")
+            .or_else(|| number.strip_prefix("0X"))
+        {
+            Some(hex) => Tokenizer::parse_hex_float(hex),
+            None => number.parse::<f64>().ok(),
+        };
+        match val {
+            Some(val) if number.len() + 1 >= string.len() => val,
+            _ => {
+                self.report_at(loc, "invalid floating constant");
+                process::exit(1);
+            }
+        }
+    }
+
+    // Parse the part of a hexadecimal floating constant after "0x": hex digits
+    // with an optional point, and a binary exponent, which is mandatory.
+    fn parse_hex_float(hex: &str) -> Option<f64> {
+        let (mantissa, exponent) = hex.split_once(['p', 'P'])?;
+        let exponent: i32 = exponent.parse().ok()?;
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+
+        let mut val = 0.0;
+        for c in int_part.chars().chain(frac_part.chars()) {
+            val = val * 16.0 + c.to_digit(16)? as f64;
+        }
+        Some(val * 2f64.powi(exponent - 4 * frac_part.len() as i32))
+    }
+
+    // Read a string literal, translating its escape sequences.
+    fn parse_string(&mut self, loc: usize) -> Vec<u8> {
+        self.next_char();
+        let mut bytes = Vec::new();
+        loop {
+            match self.next_char() {
+                Some('"') => return bytes,
+                Some('\\') => bytes.push(self.parse_escape(loc)),
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                None => {
+                    self.report_at(loc, "unterminated string literal");
+                    process::exit(1);
+                }
+            }
         }
+    }
 
-        return Some(integer);
+    // Read the escape sequence after a backslash: up to three octal digits,
+    // "x" and hex digits, or a single character.
+    fn parse_escape(&mut self, loc: usize) -> u8 {
+        let c = match self.next_char() {
+            Some(c) => c,
+            None => {
+                self.report_at(loc, "unterminated string literal");
+                process::exit(1);
+            }
+        };
+        if let Some(digit) = c.to_digit(8) {
+            let mut val = digit;
+            for _ in 0..2 {
+                match self.chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => val = val * 8 + digit,
+                    None => break,
+                }
+                self.next_char();
+            }
+            return val as u8;
+        }
+        if c == 'x' {
+            let mut val: u32 = 0;
+            let mut digits = 0;
+            while let Some(digit) = self.chars.peek().and_then(|c| c.to_digit(16)) {
+                val = val.wrapping_mul(16).wrapping_add(digit);
+                digits += 1;
+                self.next_char();
+            }
+            if digits == 0 {
+                self.report_at(self.pos, "\\x used with no following hex digits");
+                process::exit(1);
+            }
+            return val as u8;
+        }
+        match c {
+            'a' => 7,
+            'b' => 8,
+            't' => b'\t',
+            'n' => b'\n',
+            'v' => 11,
+            'f' => 12,
+            'r' => b'\r',
+            'e' => 27, // GNU extension
+            _ => c as u8,
+        }
     }
 
-    fn peek_and_append_char(
-        tokenizer: &mut Tokenizer,
-        mut string: String,
-        expected: char,
-    ) -> String {
-        let next_char = tokenizer.chars.peek();
-        match next_char {
-            Some(c) if c == &expected => string.push(tokenizer.chars.next().unwrap()),
-            None => {}
-            _ => eprintln!("tokenizer: not implemented operator"),
+    // Read the longest punctuator starting at the current character.
+    fn parse_punct(tokenizer: &mut Tokenizer) -> String {
+        let mut string = String::new();
+        while let Some(&c) = tokenizer.chars.peek() {
+            let mut candidate = string.clone();
+            candidate.push(c);
+            if !PUNCTUATORS.iter().any(|p| p.starts_with(&candidate)) {
+                break;
+            }
+            string = candidate;
+            tokenizer.next_char();
         }
-        return string;
+
+        if !PUNCTUATORS.contains(&string.as_str()) {
+            eprintln!("tokenizer: unexpected token '{}'", string);
+            process::exit(1);
+        }
+        string
     }
 
     fn parse_ident(tokenizer: &mut Tokenizer, mut string: String) -> String {
         loop {
-            let next_char = tokenizer.chars.peek();
+            let next_char = tokenizer.chars.peek().copied();
             match next_char {
-                Some('a'..='z') => string.push(tokenizer.chars.next().unwrap()),
-                Some('A'..='Z') => string.push(tokenizer.chars.next().unwrap()),
-                Some('0'..='9') => string.push(tokenizer.chars.next().unwrap()),
-                Some('_') => string.push(tokenizer.chars.next().unwrap()),
+                Some('a'..='z') => string.push(tokenizer.next_char().unwrap()),
+                Some('A'..='Z') => string.push(tokenizer.next_char().unwrap()),
+                Some('0'..='9') => string.push(tokenizer.next_char().unwrap()),
+                Some('_') => string.push(tokenizer.next_char().unwrap()),
                 None => break,
                 _ => break,
             }
         }
-        return string;
+        string
     }
 
     fn convert_keyword(&self, string: &str) -> TokenKind {
-        let keywords = vec!["return", "if", "else", "for", "while"];
+        let keywords = vec![
+            "return",
+            "if",
+            "else",
+            "for",
+            "while",
+            "do",
+            "break",
+            "continue",
+            "goto",
+            "switch",
+            "case",
+            "default",
+            "char",
+            "short",
+            "int",
+            "long",
+            "signed",
+            "unsigned",
+            "float",
+            "double",
+            "void",
+            "struct",
+            "sizeof",
+            "union",
+            "enum",
+            "typedef",
+            "static",
+            "extern",
+            "const",
+            "volatile",
+            "restrict",
+            "_Bool",
+            "_Alignas",
+            "_Generic",
+            "_Static_assert",
+        ];
 
         for kw in keywords {
             if string == kw {
                 return TokenKind::TkKeyword;
             }
         }
-        return TokenKind::TkIdent;
+        TokenKind::TkIdent
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    fn parse_int(input: &str) -> IntLiteral {
+        Tokenizer::new_empty(input).parse_int(0)
+    }
+
     #[test]
     fn test_parse_int() {
-        let mut tokenizer = Tokenizer::new_empty("42".chars().peekable());
-        assert_eq!(tokenizer.parse_int().unwrap(), String::from("42"));
+        let literal = parse_int("42");
+        assert_eq!((literal.val, literal.is_decimal), (42, true));
+        assert_eq!(parse_int("1 2").val, 1);
+        assert_eq!(parse_int("0x1F").val, 31);
+        assert_eq!(parse_int("0b101").val, 5);
+        assert_eq!(parse_int("017").val, 15);
+        assert_eq!(parse_int("0").val, 0);
+        assert_eq!(parse_int("0xffffffffffffffff").val, u64::MAX);
+        let literal = parse_int("10lu");
+        assert_eq!((literal.is_unsigned, literal.longs), (true, 1));
+        let literal = parse_int("0X10LL;");
+        assert_eq!(
+            (literal.is_decimal, literal.is_unsigned, literal.longs),
+            (false, false, 2)
+        );
+    }
+
+    #[test]
+    fn test_parse_float() {
+        let tokenizer = Tokenizer::new_empty("1.5e3;");
+        assert_eq!(tokenizer.float_literal_len(), Some(5));
+        assert_eq!(tokenizer.parse_float("1.5e3", 0), 1500.0);
+        assert_eq!(tokenizer.parse_float(".25f", 0), 0.25);
+        assert_eq!(tokenizer.parse_float("0x1.8p1", 0), 3.0);
+        assert_eq!(tokenizer.parse_float("0X10P-2", 0), 4.0);
+        let tokenizer = Tokenizer::new_empty("1e-2-1");
+        assert_eq!(tokenizer.float_literal_len(), Some(4));
+        let tokenizer = Tokenizer::new_empty("0x1e+1");
+        assert_eq!(tokenizer.float_literal_len(), None);
+        let tokenizer = Tokenizer::new_empty(".x");
+        assert_eq!(tokenizer.float_literal_len(), None);
+    }
+
+    #[test]
+    fn test_parse_string() {
+        let parse_string = |input| Tokenizer::new_empty(input).parse_string(0);
+        assert_eq!(parse_string("\"abc\" x"), b"abc");
+        assert_eq!(parse_string(r#""a\"b\\""#), b"a\"b\\");
+        assert_eq!(parse_string(r#""\n\t\0""#), b"\n\t\0");
+        assert_eq!(parse_string(r#""\101\1012\x41g""#), b"AA2Ag");
+    }
+
+    #[test]
+    fn test_parse_punct() {
+        let mut tokenizer = Tokenizer::new_empty("<<=1");
+        assert_eq!(Tokenizer::parse_punct(&mut tokenizer), String::from("<<="));
+        let mut tokenizer = Tokenizer::new_empty("&&x");
+        assert_eq!(Tokenizer::parse_punct(&mut tokenizer), String::from("&"));
     }
 }
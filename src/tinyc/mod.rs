@@ -1,3 +1,4 @@
 pub mod codegen;
 pub mod lexer;
 pub mod parser;
+pub mod types;
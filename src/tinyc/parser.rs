@@ -1,4 +1,5 @@
-use super::lexer::Tokenizer;
+use super::lexer::{IntLiteral, Tokenizer};
+use super::types::{align_to, Member, MemberDecl, Type, TypeKind};
 use std::process;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -7,19 +8,47 @@ pub enum NodeKind {
     NodeSub,
     NodeMul,
     NodeDiv,
+    NodeMod,
+    NodeBitAnd,
+    NodeBitOr,
+    NodeBitXor,
+    NodeBitNot,
+    NodeShl,
+    NodeShr,
     NodeNum,
     NodeEQ,
     NodeNE,
     NodeLT,
     NodeLE,
     NodeAssign,
+    NodeAssignOp, // compound assignment: rhs computes the new value from NodeOldValue
+    NodeOldValue, // the current value of a compound assignment's lhs, already pushed
+    NodeCond,     // "?:" operator
+    NodeComma,
     NodeAddr,
     NodeDeref,
     NodeIf,
     NodeFor, // for statement & while statement
+    NodeDo,
+    NodeBreak,
+    NodeContinue,
+    NodeGoto,
+    NodeLabel,
+    NodeSwitch,
+    NodeCase, // case or default label; lhs is the labeled statement
     NodeReturn,
     NodeBlock,
+    NodeStmtExpr,        // "({ ... })": the value of the last statement in body
+    NodeCompoundLiteral, // lhs initializes the unnamed object rhs
     NodeLVar,
+    NodeCast,
+    NodeFuncall,
+    NodeMember,  // "." operator; "->" is a member of a dereference
+    NodeMemZero, // zero-fill `var` before its initializer is stored
+    NodeVaStart, // va_start(lhs, ...)
+    NodeVaArg,   // va_arg(lhs, ty)
+    NodeVla,     // allocate lhs bytes for the variable-length array `var`
+    NodeAlloca,  // __builtin_alloca(lhs)
 }
 
 type Tree = Option<Box<Node>>;
@@ -29,128 +58,2477 @@ pub struct Node {
     pub kind: NodeKind,
     pub lhs: Tree,
     pub rhs: Tree,
-    pub val: Option<String>,
-    pub offset: usize,
+    pub val: Option<String>, // label name, or the callee of NodeFuncall
+    pub ival: i64,           // integer constant
+    pub fval: f64,           // floating constant
+    pub var: Option<LVar>,
+    pub member: Option<Member>,
+    pub ty: Option<Type>, // type of an expression
     pub body: Vec<Tree>,
 
-    // for NodeIf or NodeFor
-    pub cond: Tree,
-    pub then: Tree,
-    pub els: Tree,
-    pub init: Tree,
-    pub inc: Tree,
-}
+    // for NodeIf, NodeFor or NodeCond
+    pub cond: Tree,
+    pub then: Tree,
+    pub els: Tree,
+    pub init: Tree,
+    pub inc: Tree,
+
+    // for NodeSwitch: the case values in source order, and whether a default
+    // label exists. For NodeCase: the index into the switch's cases, or None
+    // for the default label.
+    pub cases: Vec<i64>,
+    pub has_default: bool,
+    pub case_index: Option<usize>,
+
+    // for NodeFuncall, already converted to the parameter types
+    pub args: Vec<Tree>,
+}
+
+impl Node {
+    // Call `f` on this node and on every node below it.
+    fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Node)) {
+        f(self);
+        let children = vec![
+            &mut self.lhs,
+            &mut self.rhs,
+            &mut self.cond,
+            &mut self.then,
+            &mut self.els,
+            &mut self.init,
+            &mut self.inc,
+        ];
+        let lists = self.body.iter_mut().chain(self.args.iter_mut());
+        for child in children.into_iter().chain(lists).flatten() {
+            child.visit_mut(f);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LVar {
+    pub name: String,
+    pub offset: usize, // from rbp, for a local variable
+    pub ty: Type,
+    // declared by its first use rather than by a declaration
+    implicit: bool,
+    pub is_global: bool,
+}
+
+// A variable with static storage: a global variable or a string literal.
+// Its initial contents are `init_data`, or all zero if it is None, where each
+// relocation stands for the 8-byte address of a symbol plus an addend.
+#[derive(Debug, Clone)]
+pub struct GlobalVar {
+    pub name: String,
+    pub ty: Type,
+    pub is_static: bool,     // not visible to other object files
+    pub is_definition: bool, // false if only declared `extern`
+    pub init_data: Option<Vec<u8>>,
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub offset: usize,
+    pub label: String,
+    pub addend: i64,
+}
+
+// The initializer of a variable, shaped like its type: an array or a struct
+// has one child per element or member, and a scalar has the expression that
+// initializes it, if any. A struct may also be initialized by an expression
+// of its type.
+#[derive(Debug, Clone)]
+struct Initializer {
+    ty: Type,
+    expr: Tree,
+    children: Vec<Initializer>,
+    // an array of unknown length, which grows to fit its elements
+    is_flexible: bool,
+    // for a union: the only member that is initialized
+    union_member: Option<usize>,
+}
+
+impl Initializer {
+    fn new(ty: &Type, is_flexible: bool) -> Self {
+        let mut init = Initializer {
+            ty: ty.clone(),
+            expr: None,
+            children: Vec::new(),
+            is_flexible: false,
+            union_member: None,
+        };
+        if ty.is_array() {
+            match ty.array_len {
+                Some(len) => {
+                    let base = ty.base.as_ref().unwrap();
+                    init.children = (0..len).map(|_| Initializer::new(base, false)).collect();
+                }
+                None => init.is_flexible = is_flexible,
+            }
+        } else if let Some(ref members) = ty.members {
+            init.children = members
+                .iter()
+                .map(|member| Initializer::new(&member.ty, false))
+                .collect();
+        }
+        init
+    }
+
+    // Make room for the element at `index` of an array of unknown length.
+    fn grow_to(&mut self, index: usize) {
+        if self.is_flexible {
+            let base = self.ty.base.as_ref().unwrap();
+            while self.children.len() <= index {
+                self.children.push(Initializer::new(base, false));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub ty: Type,
+    pub is_static: bool, // not visible to other object files
+    pub params: Vec<LVar>,
+    pub body: Tree, // None for a declaration without a definition
+    pub stack_size: usize,
+    // the alignment the frame needs: that of the stack, 16, unless a local
+    // is aligned beyond it
+    pub stack_align: usize,
+    // for a variadic function: where the prologue saves the argument
+    // registers, for va_arg to read the unnamed arguments from
+    pub va_area: Option<LVar>,
+    // for a function returning a struct in memory: where the prologue saves
+    // the address of the caller's buffer to return it in
+    pub ret_ptr: Option<LVar>,
+}
+
+// The size of the register save area of a variadic function: the six
+// integer argument registers, then the eight 16-byte xmm ones.
+pub const VA_AREA_SIZE: usize = 6 * 8 + 8 * 16;
+
+// What an ordinary identifier stands for. Variables, typedef names and enum
+// constants share one name space.
+#[derive(Debug, Clone)]
+enum Symbol {
+    Var(LVar),
+    Typedef(Type),
+    EnumConst(i64),
+}
+
+// A block scope. Identifiers and tags are only visible in the scope that
+// declares them and the scopes nested in it. The outermost scope holds the
+// built-in names, and the file scope is nested in it so that declarations
+// can shadow them.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    symbols: Vec<(String, Symbol)>,
+    tags: Vec<(String, Type)>,
+    // bytes of the frame in use by this scope and the ones enclosing it, so
+    // that sibling scopes reuse the same slots
+    offset: usize,
+    // the variable-length arrays declared in this scope, in order. Leaving
+    // the scope frees them, by restoring the stack pointer saved by the first.
+    vlas: Vec<LVar>,
+}
+
+// The index of the file scope in `Parser::scopes`
+const FILE_SCOPE: usize = 1;
+
+// The storage-class specifiers of a declaration.
+#[derive(Debug, Clone, Default)]
+struct VarAttr {
+    is_typedef: bool,
+    is_static: bool,
+    is_extern: bool,
+    // from _Alignas, or 0 if the declared objects keep their natural
+    // alignment
+    align: usize,
+}
+
+// The case labels seen so far in a switch statement being parsed, with the
+// location of each one for duplicate diagnostics.
+#[derive(Debug, Clone)]
+struct SwitchScope {
+    ty: Type, // promoted type of the controlling expression
+    cases: Vec<(i64, usize)>,
+    default_loc: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Parser<'a> {
+    lexer: Tokenizer<'a>,
+    pub functions: Vec<Function>,
+    pub globals: Vec<GlobalVar>,
+
+    // state of the function being parsed
+    ret_ty: Type,
+    va_area: Option<LVar>,
+    // the parameters of the last function declarator parsed, for the
+    // definition of the function to declare
+    last_params: Vec<(Option<String>, Type, usize)>,
+    scopes: Vec<Scope>,
+    stack_size: usize,
+    stack_align: usize,
+    // each label with the number of variable-length arrays in scope at it,
+    // and each goto with the ones in scope at the goto, both with the
+    // statement expressions they are in
+    labels: Vec<(String, usize, Vec<usize>)>,
+    gotos: Vec<(String, Vec<LVar>, Vec<usize>)>,
+    // the statement expressions being parsed, numbered in order
+    stmt_exprs: Vec<usize>,
+    stmt_expr_count: usize,
+    switches: Vec<SwitchScope>,
+    // the number of variable-length arrays in scope at the start of the
+    // enclosing loops and switches, which break and continue free down to
+    break_vlas: Vec<usize>,
+    continue_vlas: Vec<usize>,
+    // assignments that compute the sizes of the variable-length array types
+    // parsed since the enclosing declaration or expression took them
+    vla_setup: Vec<Tree>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn parse(lexer: Tokenizer<'a>) -> Self {
+        let mut parser = Parser {
+            lexer,
+            functions: Vec::new(),
+            globals: Vec::new(),
+            ret_ty: Type::int(false),
+            va_area: None,
+            last_params: Vec::new(),
+            scopes: vec![Scope::default(), Scope::default()],
+            stack_size: 0,
+            stack_align: 16,
+            labels: Vec::new(),
+            gotos: Vec::new(),
+            stmt_exprs: Vec::new(),
+            stmt_expr_count: 0,
+            switches: Vec::new(),
+            break_vlas: Vec::new(),
+            continue_vlas: Vec::new(),
+            vla_setup: Vec::new(),
+        };
+        parser.declare_builtin_types();
+        parser.program();
+        parser
+    }
+
+    // va_list is built in, with the layout the SysV ABI gives it, so that it
+    // can be passed to the C library's v*printf functions.
+    fn declare_builtin_types(&mut self) {
+        let mut elem = Type::incomplete_struct(None);
+        elem.layout(vec![
+            (String::from("gp_offset"), Type::int(true)),
+            (String::from("fp_offset"), Type::int(true)),
+            (
+                String::from("overflow_arg_area"),
+                Type::pointer_to(Type::void()),
+            ),
+            (
+                String::from("reg_save_area"),
+                Type::pointer_to(Type::void()),
+            ),
+        ]);
+        let va_list = Type::array_of(elem, Some(1));
+        self.scopes[0]
+            .symbols
+            .push((String::from("va_list"), Symbol::Typedef(va_list)));
+
+        // There is no preprocessor to include <stdbool.h>, so what it
+        // defines is built in as well.
+        let stdbool = [
+            (String::from("bool"), Symbol::Typedef(Type::bool())),
+            (String::from("true"), Symbol::EnumConst(1)),
+            (String::from("false"), Symbol::EnumConst(0)),
+        ];
+        self.scopes[0].symbols.extend(stdbool);
+    }
+
+    fn new_node(&mut self, kind: NodeKind, lhs: Tree, rhs: Tree) -> Tree {
+        let mut node = self.new_raw_node(kind, lhs, rhs);
+        self.add_type(&mut node);
+        Some(Box::new(node))
+    }
+
+    fn new_raw_node(&self, kind: NodeKind, lhs: Tree, rhs: Tree) -> Node {
+        Node {
+            kind,
+            lhs,
+            rhs,
+            val: None,
+            ival: 0,
+            fval: 0.0,
+            var: None,
+            member: None,
+            ty: None,
+            body: Vec::new(),
+            cond: None,
+            then: None,
+            els: None,
+            init: None,
+            inc: None,
+            cases: Vec::new(),
+            has_default: false,
+            case_index: None,
+            args: Vec::new(),
+        }
+    }
+
+    fn new_node_num(&self, val: i64) -> Tree {
+        let mut node = self.new_raw_node(NodeKind::NodeNum, None, None);
+        // a decimal constant is an int if it fits, and a long otherwise
+        node.ty = if val == val as i32 as i64 {
+            Some(Type::int(false))
+        } else {
+            Some(Type::long(false))
+        };
+        node.ival = val;
+        Some(Box::new(node))
+    }
+
+    // The type of an integer constant is the first one that can represent it
+    // among the candidates for its suffix and base, see C11 6.4.4.1.
+    fn new_node_literal(&self, literal: IntLiteral, loc: usize) -> Tree {
+        let kinds = [Type::int, Type::long, Type::long_long];
+        let mut candidates = Vec::new();
+        for kind in kinds.iter().skip(literal.longs) {
+            if !literal.is_unsigned {
+                candidates.push(kind(false));
+            }
+            if literal.is_unsigned || !literal.is_decimal {
+                candidates.push(kind(true));
+            }
+        }
+
+        let fits = |ty: &Type| match (ty.size, ty.is_unsigned) {
+            (4, false) => literal.val <= i32::MAX as u64,
+            (4, true) => literal.val <= u32::MAX as u64,
+            (_, false) => literal.val <= i64::MAX as u64,
+            (_, true) => true,
+        };
+        match candidates.into_iter().find(fits) {
+            Some(ty) => {
+                let mut node = self.new_raw_node(NodeKind::NodeNum, None, None);
+                node.ival = literal.val as i64;
+                node.ty = Some(ty);
+                Some(Box::new(node))
+            }
+            None => {
+                self.lexer
+                    .report_at(loc, "integer constant is too large for its type");
+                process::exit(1);
+            }
+        }
+    }
+
+    fn new_node_float(&self, fval: f64, is_float: bool) -> Tree {
+        let mut node = self.new_raw_node(NodeKind::NodeNum, None, None);
+        node.ty = Some(if is_float {
+            Type::float()
+        } else {
+            Type::double()
+        });
+        node.fval = fval;
+        Some(Box::new(node))
+    }
+
+    fn new_node_var(&self, var: LVar) -> Tree {
+        let mut node = self.new_raw_node(NodeKind::NodeLVar, None, None);
+        node.ty = Some(var.ty.clone());
+        node.var = Some(var);
+        Some(Box::new(node))
+    }
+
+    fn new_cast(&self, expr: Tree, ty: Type) -> Tree {
+        let mut node = self.new_raw_node(NodeKind::NodeCast, expr, None);
+        node.ty = Some(ty);
+        Some(Box::new(node))
+    }
+
+    fn ty_of(node: &Tree) -> Type {
+        node.as_ref().unwrap().ty.clone().unwrap()
+    }
+
+    fn ty_of_node(node: &Node) -> &Type {
+        node.ty.as_ref().unwrap()
+    }
+
+    // Convert both operands to their common type.
+    fn usual_arith_conv(&self, node: &mut Node) -> Type {
+        let lhs_ty = Parser::ty_of(&node.lhs);
+        let rhs_ty = Parser::ty_of(&node.rhs);
+        if !lhs_ty.is_arith() || !rhs_ty.is_arith() {
+            eprintln!("parser: invalid operands to {:?}", node.kind);
+            process::exit(1);
+        }
+        let ty = Type::common(&lhs_ty, &rhs_ty);
+        node.lhs = self.new_cast(node.lhs.take(), ty.clone());
+        node.rhs = self.new_cast(node.rhs.take(), ty.clone());
+        ty
+    }
+
+    // Set the type of an expression node, inserting the implicit conversions
+    // its operands need.
+    fn add_type(&self, node: &mut Node) {
+        if node.ty.is_some() {
+            return;
+        }
+
+        match node.kind {
+            NodeKind::NodeAdd | NodeKind::NodeSub | NodeKind::NodeMul | NodeKind::NodeDiv => {
+                node.ty = Some(self.usual_arith_conv(node));
+            }
+            NodeKind::NodeMod
+            | NodeKind::NodeBitAnd
+            | NodeKind::NodeBitOr
+            | NodeKind::NodeBitXor => {
+                Parser::check_integer_operands(node);
+                node.ty = Some(self.usual_arith_conv(node));
+            }
+            NodeKind::NodeShl | NodeKind::NodeShr => {
+                Parser::check_integer_operands(node);
+                // each operand is promoted on its own
+                let lhs_ty = Parser::ty_of(&node.lhs).promote();
+                let rhs_ty = Parser::ty_of(&node.rhs).promote();
+                node.lhs = self.new_cast(node.lhs.take(), lhs_ty.clone());
+                node.rhs = self.new_cast(node.rhs.take(), rhs_ty);
+                node.ty = Some(lhs_ty);
+            }
+            NodeKind::NodeEQ | NodeKind::NodeNE | NodeKind::NodeLT | NodeKind::NodeLE => {
+                if Parser::ty_of(&node.lhs).is_struct() || Parser::ty_of(&node.rhs).is_struct() {
+                    eprintln!("parser: invalid operands to {:?}", node.kind);
+                    process::exit(1);
+                }
+                if Parser::ty_of(&node.lhs).is_arith() && Parser::ty_of(&node.rhs).is_arith() {
+                    self.usual_arith_conv(node);
+                }
+                node.ty = Some(Type::int(false));
+            }
+            NodeKind::NodeBitNot => {
+                Parser::check_integer_operands(node);
+                let ty = Parser::ty_of(&node.lhs).promote();
+                node.lhs = self.new_cast(node.lhs.take(), ty.clone());
+                node.ty = Some(ty);
+            }
+            NodeKind::NodeAssign => {
+                let ty = self.complete_type(Parser::ty_of(&node.lhs));
+                if ty.is_array() {
+                    eprintln!("parser: array type is not assignable");
+                    process::exit(1);
+                }
+                let rhs_ty = self.complete_type(Parser::ty_of(&node.rhs));
+                if rhs_ty.kind == TypeKind::TyVoid {
+                    eprintln!("parser: void value not ignored as it ought to be");
+                    process::exit(1);
+                }
+                // structs are copied, and only to a struct of the same type
+                if (ty.is_struct() || rhs_ty.is_struct())
+                    && ty.unqualified() != rhs_ty.unqualified()
+                {
+                    eprintln!("parser: incompatible types in assignment");
+                    process::exit(1);
+                }
+                // a pointer stored to a _Bool is converted too, to 0 or 1
+                if ty.is_arith() && (rhs_ty.is_arith() || ty.kind == TypeKind::TyBool) {
+                    node.rhs = self.new_cast(node.rhs.take(), ty.clone());
+                }
+                node.ty = Some(ty);
+            }
+            NodeKind::NodeAssignOp => {
+                node.ty = Some(Parser::ty_of(&node.lhs));
+            }
+            NodeKind::NodeCond => {
+                let then_ty = Parser::ty_of(&node.then);
+                let els_ty = Parser::ty_of(&node.els);
+                if then_ty.is_arith() && els_ty.is_arith() {
+                    let ty = Type::common(&then_ty, &els_ty);
+                    node.then = self.new_cast(node.then.take(), ty.clone());
+                    node.els = self.new_cast(node.els.take(), ty.clone());
+                    node.ty = Some(ty);
+                } else {
+                    node.ty = Some(then_ty);
+                }
+            }
+            NodeKind::NodeComma | NodeKind::NodeCompoundLiteral => {
+                node.ty = Some(Parser::ty_of(&node.rhs));
+            }
+            NodeKind::NodeAddr => {
+                let lhs = node.lhs.as_ref().unwrap();
+                // the address of an implicitly declared variable is a plain
+                // integer, as it was before variables had types, so that
+                // `&x - 8` still counts bytes
+                node.ty = match lhs.var {
+                    Some(ref var) if var.implicit => Some(Type::long(false)),
+                    _ => Some(Type::pointer_to(Parser::ty_of(&node.lhs))),
+                };
+            }
+            NodeKind::NodeDeref => {
+                // dereferencing an integer computed from implicitly declared
+                // variables reads a long, which is what those variables
+                // holding addresses rely on
+                let ty = Parser::ty_of(&node.lhs);
+                node.ty = match ty.base {
+                    Some(base) => Some(self.complete_type(*base)),
+                    // a function designator is converted to a pointer to
+                    // the function, which dereferences back to it
+                    None if ty.is_function() => Some(ty),
+                    None if ty.is_integer() && Parser::is_implicit(node.lhs.as_ref().unwrap()) => {
+                        Some(Type::long(false))
+                    }
+                    None => {
+                        eprintln!("parser: invalid type argument of unary '*'");
+                        process::exit(1);
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+
+    // Whether an expression is computed from implicitly declared variables.
+    fn is_implicit(node: &Node) -> bool {
+        if node.var.as_ref().is_some_and(|var| var.implicit) {
+            return true;
+        }
+        [&node.lhs, &node.rhs]
+            .iter()
+            .any(|operand| operand.as_deref().is_some_and(Parser::is_implicit))
+    }
+
+    // Bitwise operators and "%" are only defined for integers.
+    fn check_integer_operands(node: &Node) {
+        let is_integer = |operand: &Tree| operand.is_none() || Parser::ty_of(operand).is_integer();
+        if !is_integer(&node.lhs) || !is_integer(&node.rhs) {
+            eprintln!("parser: invalid operands to {:?}", node.kind);
+            process::exit(1);
+        }
+    }
+
+    // Pointer arithmetic scales the integer operand by the pointee size.
+    fn new_add(&mut self, lhs: Tree, rhs: Tree) -> Tree {
+        let lhs_ty = Parser::ty_of(&lhs);
+        let rhs_ty = Parser::ty_of(&rhs);
+
+        if lhs_ty.is_arith() && rhs_ty.is_arith() {
+            return self.new_node(NodeKind::NodeAdd, lhs, rhs);
+        }
+        // only `ptr + num` and `num + ptr` are left
+        if !(lhs_ty.is_pointer() && rhs_ty.is_integer()
+            || lhs_ty.is_integer() && rhs_ty.is_pointer())
+        {
+            eprintln!("parser: invalid operands to binary +");
+            process::exit(1);
+        }
+
+        // canonicalize `num + ptr` to `ptr + num`
+        let (ptr, num, ty) = if lhs_ty.is_pointer() {
+            (lhs, rhs, lhs_ty)
+        } else {
+            (rhs, lhs, rhs_ty)
+        };
+        let num = self.new_scaled(num, &ty);
+        let mut node = self.new_raw_node(NodeKind::NodeAdd, ptr, num);
+        node.ty = Some(Type::pointer_to(*ty.base.unwrap()));
+        Some(Box::new(node))
+    }
+
+    fn new_sub(&mut self, lhs: Tree, rhs: Tree) -> Tree {
+        let lhs_ty = Parser::ty_of(&lhs);
+        let rhs_ty = Parser::ty_of(&rhs);
+
+        if lhs_ty.is_arith() && rhs_ty.is_arith() {
+            return self.new_node(NodeKind::NodeSub, lhs, rhs);
+        }
+
+        // ptr - num
+        if lhs_ty.is_pointer() && rhs_ty.is_integer() {
+            let rhs = self.new_scaled(rhs, &lhs_ty);
+            let mut node = self.new_raw_node(NodeKind::NodeSub, lhs, rhs);
+            node.ty = Some(Type::pointer_to(*lhs_ty.base.unwrap()));
+            return Some(Box::new(node));
+        }
+
+        // ptr - ptr, which is the number of elements between the two
+        if lhs_ty.is_pointer() && rhs_ty.is_pointer() {
+            let mut diff = self.new_raw_node(NodeKind::NodeSub, lhs, rhs);
+            diff.ty = Some(Type::long(false));
+            let size = self.elem_size(&lhs_ty);
+            return self.new_node(NodeKind::NodeDiv, Some(Box::new(diff)), size);
+        }
+
+        eprintln!("parser: invalid operands to binary -");
+        process::exit(1);
+    }
+
+    fn new_scaled(&mut self, num: Tree, ptr_ty: &Type) -> Tree {
+        let num = self.new_cast(num, Type::long(false));
+        let size = self.elem_size(ptr_ty);
+        self.new_node(NodeKind::NodeMul, num, size)
+    }
+
+    // The size of what a pointer of type `ptr_ty` points to, as a long.
+    fn elem_size(&mut self, ptr_ty: &Type) -> Tree {
+        let base = ptr_ty.base.as_ref().unwrap();
+        if base.is_vla() {
+            let size = self.size_of(base);
+            return self.new_cast(size, Type::long(false));
+        }
+        self.new_node_num(base.size as i64)
+    }
+
+    // The size of `ty` in bytes, as an unsigned long. The size of a
+    // variable-length array is read from the local it was computed into.
+    fn size_of(&self, ty: &Type) -> Tree {
+        if let Some(offset) = ty.vla_size {
+            return self.new_node_var(Parser::vla_size_var(offset));
+        }
+        let mut raw_node = self.new_raw_node(NodeKind::NodeNum, None, None);
+        raw_node.ival = ty.size as i64;
+        raw_node.ty = Some(Type::long(true));
+        Some(Box::new(raw_node))
+    }
+
+    fn vla_size_var(offset: usize) -> LVar {
+        LVar {
+            name: String::new(),
+            offset,
+            ty: Type::long(true),
+            implicit: false,
+            is_global: false,
+        }
+    }
+
+    // Run the size computations of the variable-length array types parsed
+    // for an expression before the expression itself.
+    fn with_vla_setup(&mut self, node: Tree) -> Tree {
+        if self.vla_setup.is_empty() {
+            return node;
+        }
+        let mut setup = self.new_raw_node(NodeKind::NodeStmtExpr, None, None);
+        setup.body = std::mem::take(&mut self.vla_setup);
+        setup.ty = Some(Type::void());
+        self.new_node(NodeKind::NodeComma, Some(Box::new(setup)), node)
+    }
+
+    // The variable-length arrays in scope, outermost first.
+    fn vla_chain(&self) -> Vec<LVar> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.vlas.iter().cloned())
+            .collect()
+    }
+
+    // The array to free down to when jumping to where `depth` of the
+    // variable-length arrays now in scope remain in scope.
+    fn vlas_left(&self, depth: Option<usize>) -> Option<LVar> {
+        self.vla_chain().get(depth?).cloned()
+    }
+
+    // Build any binary operator, including the pointer arithmetic ones.
+    fn new_binary(&mut self, kind: NodeKind, lhs: Tree, rhs: Tree) -> Tree {
+        match kind {
+            NodeKind::NodeAdd => self.new_add(lhs, rhs),
+            NodeKind::NodeSub => self.new_sub(lhs, rhs),
+            _ => self.new_node(kind, lhs, rhs),
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        let offset = self.scopes.last().unwrap().offset;
+        self.scopes.push(Scope {
+            symbols: Vec::new(),
+            tags: Vec::new(),
+            offset,
+            vlas: Vec::new(),
+        });
+    }
+
+    fn leave_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn in_file_scope(&self) -> bool {
+        self.scopes.len() == FILE_SCOPE + 1
+    }
+
+    // Look an identifier up from the innermost scope outward.
+    fn find_symbol(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .symbols
+                .iter()
+                .rev()
+                .find(|(symbol, _)| symbol == name)
+                .map(|(_, symbol)| symbol)
+        })
+    }
+
+    fn declare_symbol(&mut self, name: String, symbol: Symbol, loc: usize) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.symbols.iter().any(|(prev, _)| *prev == name) {
+            self.lexer
+                .report_at(loc, &format!("redefinition of '{}'", name));
+            process::exit(1);
+        }
+        scope.symbols.push((name, symbol));
+    }
+
+    // Look a variable up from the innermost scope outward. An undeclared
+    // variable is implicitly declared as a long in the function's outermost
+    // scope.
+    pub fn find_var(&mut self, val: String) -> LVar {
+        if let Some(Symbol::Var(var)) = self.find_symbol(&val) {
+            return var.clone();
+        }
+        if self.in_file_scope() {
+            eprintln!("parser: use of undeclared identifier '{}'", val);
+            process::exit(1);
+        }
+
+        // the slot must not overlap any scope that is still open, nor the
+        // ones opened later, so every open scope is moved past it
+        let offset = align_to(self.scopes.last().unwrap().offset, 8) + 8;
+        for scope in self.scopes.iter_mut() {
+            scope.offset = offset;
+        }
+        self.stack_size = self.stack_size.max(offset);
+        let var = LVar {
+            name: val,
+            offset,
+            ty: Type::long(false),
+            implicit: true,
+            is_global: false,
+        };
+        self.scopes[FILE_SCOPE + 1]
+            .symbols
+            .push((var.name.clone(), Symbol::Var(var.clone())));
+        var
+    }
+
+    fn declare_var(&mut self, name: String, ty: Type, loc: usize) -> LVar {
+        if !ty.is_complete() {
+            self.lexer
+                .report_at(loc, &format!("'{}' has an incomplete type", name));
+            process::exit(1);
+        }
+        let var = self.new_lvar(name, ty);
+        self.declare_symbol(var.name.clone(), Symbol::Var(var.clone()), loc);
+        var
+    }
+
+    // Allocate a local in the current scope's part of the stack frame. The
+    // slot of a variable-length array holds the address of its storage, and
+    // above it the stack pointer from before the storage was allocated.
+    fn new_lvar(&mut self, name: String, ty: Type) -> LVar {
+        let offset = self.scopes.last().unwrap().offset;
+        let (size, align) = if ty.is_vla() {
+            (16, 8)
+        } else {
+            (ty.size, ty.align)
+        };
+        let var = LVar {
+            name,
+            offset: align_to(offset + size, align),
+            ty,
+            implicit: false,
+            is_global: false,
+        };
+        self.scopes.last_mut().unwrap().offset = var.offset;
+        self.stack_size = self.stack_size.max(var.offset);
+        self.stack_align = self.stack_align.max(align);
+        var
+    }
+
+    // Declare a variable with static storage duration. A variable with
+    // linkage may be declared any number of times, and every declaration
+    // refers to the one variable. A static local has no linkage, so it is a
+    // global under a label of its own.
+    fn declare_global(&mut self, name: String, ty: Type, loc: usize, attr: &VarAttr) -> LVar {
+        if ty.is_vla() {
+            self.lexer.report_at(
+                loc,
+                &format!(
+                    "variable length array '{}' cannot have static storage",
+                    name
+                ),
+            );
+            process::exit(1);
+        }
+        let is_definition = !attr.is_extern;
+        if is_definition && !ty.is_complete() {
+            self.lexer
+                .report_at(loc, &format!("'{}' has an incomplete type", name));
+            process::exit(1);
+        }
+        let is_local_static = !self.in_file_scope() && attr.is_static;
+        let label = if is_local_static {
+            format!(".L.static.{}", self.globals.len())
+        } else {
+            name.clone()
+        };
+        let mut var = LVar {
+            name: label,
+            offset: 0,
+            ty,
+            implicit: false,
+            is_global: true,
+        };
+
+        match self
+            .globals
+            .iter_mut()
+            .find(|global| global.name == var.name)
+        {
+            Some(global) => {
+                if !Parser::is_redeclarable(&global.ty, &var.ty) {
+                    self.lexer
+                        .report_at(loc, &format!("conflicting types for '{}'", name));
+                    process::exit(1);
+                }
+                if attr.is_static && !global.is_static {
+                    self.lexer.report_at(
+                        loc,
+                        &format!("static declaration of '{}' follows non-static one", name),
+                    );
+                    process::exit(1);
+                }
+                // `extern` keeps the internal linkage of an earlier
+                // declaration, but no storage class does not
+                if global.is_static && !attr.is_static && !attr.is_extern {
+                    self.lexer.report_at(
+                        loc,
+                        &format!("non-static declaration of '{}' follows static one", name),
+                    );
+                    process::exit(1);
+                }
+                // an array may have its length only in some declarations,
+                // and the variable has the strictest alignment any of them
+                // gives it
+                let align = global.ty.align.max(var.ty.align);
+                if var.ty.is_complete() {
+                    global.ty = var.ty.clone();
+                } else {
+                    var.ty = global.ty.clone();
+                }
+                global.ty.align = align;
+                var.ty.align = align;
+                global.is_definition |= is_definition;
+            }
+            None => self.globals.push(GlobalVar {
+                name: var.name.clone(),
+                ty: var.ty.clone(),
+                is_static: attr.is_static,
+                is_definition,
+                init_data: None,
+                relocations: Vec::new(),
+            }),
+        }
+
+        let redeclared =
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .symbols
+                .iter_mut()
+                .find(|(prev, symbol)| {
+                    *prev == name && matches!(symbol, Symbol::Var(var) if var.is_global)
+                });
+        match redeclared {
+            Some((_, symbol)) if !is_local_static => *symbol = Symbol::Var(var.clone()),
+            _ => self.declare_symbol(name, Symbol::Var(var.clone()), loc),
+        }
+        var
+    }
+
+    // Whether a variable declared with type `prev` may be declared again
+    // with type `ty`.
+    fn is_redeclarable(prev: &Type, ty: &Type) -> bool {
+        // _Alignas applies to the declaration rather than the type
+        let ty = &Type {
+            align: prev.align,
+            ..ty.clone()
+        };
+        if prev.is_array() && ty.is_array() && prev.base == ty.base {
+            return prev.array_len.is_none()
+                || ty.array_len.is_none()
+                || prev.array_len == ty.array_len;
+        }
+        prev == ty
+    }
+
+    // Declare a variable and parse its initializer. An array of unknown
+    // length takes its length from the initializer, so it is only declared
+    // once that is parsed.
+    // A variable with static storage duration is declared with its
+    // attributes in `global`.
+    fn declare_initialized(
+        &mut self,
+        name: String,
+        ty: Type,
+        loc: usize,
+        global: Option<&VarAttr>,
+    ) -> (LVar, Initializer) {
+        let declare = |parser: &mut Parser, ty: Type| match global {
+            Some(attr) => parser.declare_global(name, ty, loc, attr),
+            None => parser.declare_var(name, ty, loc),
+        };
+        if ty.is_array() && ty.array_len.is_none() {
+            let (init, ty) = self.initializer(&ty);
+            (declare(self, ty), init)
+        } else {
+            let var = declare(self, ty.clone());
+            let (init, _) = self.initializer(&ty);
+            (var, init)
+        }
+    }
+
+    // A string literal is an anonymous global char array.
+    fn new_string_literal(&mut self, mut bytes: Vec<u8>) -> LVar {
+        bytes.push(0);
+        let ty = Type::array_of(Type::char(false), Some(bytes.len()));
+        let name = format!(".L.str.{}", self.globals.len());
+        self.globals.push(GlobalVar {
+            name: name.clone(),
+            ty: ty.clone(),
+            is_static: true,
+            is_definition: true,
+            init_data: Some(bytes),
+            relocations: Vec::new(),
+        });
+        LVar {
+            name,
+            offset: 0,
+            ty,
+            implicit: false,
+            is_global: true,
+        }
+    }
+
+    // program = (function | global-variable)* | compound-item*
+    //
+    // A program made of bare statements, the way programs were written before
+    // the language had functions, is the body of an implicit main.
+    fn program(&mut self) {
+        if self.is_translation_unit() {
+            while !self.lexer.at_eof() {
+                self.function();
+            }
+            return;
+        }
+
+        let ty = Type::func(Type::int(false), Vec::new());
+        self.enter_function(Type::int(false));
+        let mut raw_node = self.new_raw_node(NodeKind::NodeBlock, None, None);
+        while !self.lexer.at_eof() {
+            raw_node.body.push(self.compound_item());
+        }
+        self.check_gotos(&mut raw_node);
+        self.functions.push(Function {
+            name: String::from("main"),
+            ty,
+            is_static: false,
+            params: Vec::new(),
+            body: Some(Box::new(raw_node)),
+            stack_size: self.stack_size,
+            stack_align: self.stack_align,
+            va_area: None,
+            ret_ptr: None,
+        });
+    }
+
+    // Whether the program is made of functions, which is told apart from
+    // bare statements by a declaration of a function before the first
+    // statement.
+    fn is_translation_unit(&self) -> bool {
+        let mut probe = self.clone();
+        loop {
+            if probe.lexer.consume("_Static_assert") {
+                probe.lexer.skip_until(";");
+                continue;
+            }
+            if !probe.is_typename() {
+                break;
+            }
+            let mut attr = VarAttr::default();
+            let basety = probe.declspec(Some(&mut attr));
+            if attr.is_typedef {
+                probe.typedef_decl(basety);
+                continue;
+            }
+            if probe.lexer.consume(";") {
+                continue;
+            }
+            let (_, ty, _) = probe.declarator(basety);
+            if ty.is_function() {
+                return true;
+            }
+            probe.lexer.skip_until(";");
+        }
+        false
+    }
+
+    // Reset the per-function state before parsing a function. Its outermost
+    // scope is nested in the file scope.
+    fn enter_function(&mut self, ret_ty: Type) {
+        self.ret_ty = ret_ty;
+        self.va_area = None;
+        self.scopes.truncate(FILE_SCOPE + 1);
+        self.scopes.push(Scope {
+            symbols: Vec::new(),
+            tags: Vec::new(),
+            offset: 0,
+            vlas: Vec::new(),
+        });
+        self.stack_size = 0;
+        self.stack_align = 16;
+        self.labels.clear();
+        self.gotos.clear();
+    }
+
+    // function = declspec declarator ("{" block | ";")
+    //          | declspec declarator global-variable
+    //          | declspec ";"
+    //          | "typedef" typedef-decl
+    //          | "_Static_assert" static-assert
+    fn function(&mut self) {
+        // back in the file scope after the previous function
+        self.scopes.truncate(FILE_SCOPE + 1);
+        if self.lexer.consume("_Static_assert") {
+            self.static_assert();
+            return;
+        }
+        let mut attr = VarAttr::default();
+        let basety = self.declspec(Some(&mut attr));
+        if attr.is_typedef {
+            self.typedef_decl(basety);
+            return;
+        }
+        if self.lexer.consume(";") {
+            return;
+        }
+        let (name, ty, loc) = self.declarator(basety.clone());
+        if !ty.is_function() {
+            self.global_variable(basety, name, ty, loc, &attr);
+            return;
+        }
+
+        let param_decls = std::mem::take(&mut self.last_params);
+        self.declare_function(name.clone(), ty.clone(), loc, &attr);
+        if self.lexer.consume(";") {
+            return;
+        }
+
+        self.enter_function(*ty.return_ty.clone().unwrap());
+        let mut params = Vec::new();
+        for (param, param_ty, param_loc) in param_decls {
+            match param {
+                Some(param) => params.push(self.declare_var(param, param_ty, param_loc)),
+                None => {
+                    self.lexer.report_at(param_loc, "parameter name omitted");
+                    process::exit(1);
+                }
+            }
+        }
+        self.lexer.expect("{");
+        let ret_ty = ty.return_ty.as_ref().unwrap();
+        let ret_ptr = if ret_ty.is_struct() && ret_ty.eightbytes().is_none() {
+            let ty = Type::pointer_to(*ret_ty.clone());
+            Some(self.declare_var(String::from("__ret_ptr"), ty, loc))
+        } else {
+            None
+        };
+        if ty.is_variadic {
+            let ty = Type::array_of(Type::char(false), Some(VA_AREA_SIZE));
+            self.va_area = Some(self.declare_var(String::from("__va_area"), ty, loc));
+        }
+        let mut body = self.block();
+        self.check_gotos(body.as_mut().unwrap());
+        let function = self
+            .functions
+            .iter_mut()
+            .find(|function| function.name == name)
+            .unwrap();
+        if function.body.is_some() {
+            self.lexer
+                .report_at(loc, &format!("redefinition of '{}'", name));
+            process::exit(1);
+        }
+        function.params = params;
+        function.body = body;
+        function.stack_size = self.stack_size;
+        function.stack_align = self.stack_align;
+        function.va_area = self.va_area.clone();
+        function.ret_ptr = ret_ptr;
+    }
+
+    // Functions are declared in the file scope, any number of times as long
+    // as the declarations agree.
+    fn declare_function(&mut self, name: String, ty: Type, loc: usize, attr: &VarAttr) {
+        if attr.align != 0 {
+            self.lexer.report_at(
+                loc,
+                &format!("'_Alignas' cannot be applied to function '{}'", name),
+            );
+            process::exit(1);
+        }
+        match self.functions.iter().find(|function| function.name == name) {
+            Some(prev) if prev.ty != ty => {
+                self.lexer
+                    .report_at(loc, &format!("conflicting types for '{}'", name));
+                process::exit(1);
+            }
+            Some(prev) if attr.is_static && !prev.is_static => {
+                self.lexer.report_at(
+                    loc,
+                    &format!("static declaration of '{}' follows non-static one", name),
+                );
+                process::exit(1);
+            }
+            Some(_) => {}
+            None => self.functions.push(Function {
+                name,
+                ty,
+                is_static: attr.is_static,
+                params: Vec::new(),
+                body: None,
+                stack_size: 0,
+                stack_align: 16,
+                va_area: None,
+                ret_ptr: None,
+            }),
+        }
+    }
+
+    // global-variable = ("=" initializer)? ("," declarator ("=" initializer)?)* ";"
+    //
+    // The rest of the declaration of a variable with static storage
+    // duration, whose first declarator is parsed. Those are the variables at
+    // file scope, and the static and extern ones in a block.
+    fn global_variable(
+        &mut self,
+        basety: Type,
+        mut name: String,
+        mut ty: Type,
+        mut loc: usize,
+        attr: &VarAttr,
+    ) {
+        loop {
+            self.align_object(&mut ty, &name, loc, attr);
+            if self.lexer.consume("=") {
+                // an initializer makes an extern declaration a definition,
+                // which a block cannot have
+                if attr.is_extern && !self.in_file_scope() {
+                    self.lexer.report_at(
+                        loc,
+                        &format!("'{}' has both 'extern' and an initializer", name),
+                    );
+                    process::exit(1);
+                }
+                let attr = VarAttr {
+                    is_extern: false,
+                    ..*attr
+                };
+                let (var, init) = self.declare_initialized(name.clone(), ty, loc, Some(&attr));
+                let mut data = vec![0; var.ty.size];
+                let mut relocations = Vec::new();
+                self.write_global_data(&init, &mut data, 0, &mut relocations);
+                let global = self
+                    .globals
+                    .iter_mut()
+                    .find(|global| global.name == var.name)
+                    .unwrap();
+                if global.init_data.is_some() {
+                    self.lexer
+                        .report_at(loc, &format!("redefinition of '{}'", name));
+                    process::exit(1);
+                }
+                global.init_data = Some(data);
+                global.relocations = relocations;
+            } else if ty.is_function() {
+                self.declare_function(name, ty, loc, attr);
+            } else {
+                self.declare_global(name, ty, loc, attr);
+            }
+
+            if self.lexer.consume(";") {
+                return;
+            }
+            self.lexer.expect(",");
+            (name, ty, loc) = self.declarator(basety.clone());
+        }
+    }
+
+    // func-params = ("void" | param ("," param)* ("," "...")?)? ")"
+    //
+    // The type of a function returning `ret_ty`. The parameter names are
+    // left in `last_params`, as the type has none.
+    fn func_params(&mut self, ret_ty: Type, loc: usize) -> Type {
+        if ret_ty.is_function() || ret_ty.is_array() {
+            self.lexer
+                .report_at(loc, "function cannot return a function or an array");
+            process::exit(1);
+        }
+        let mut probe = self.lexer.clone();
+        let (params, is_variadic) = if probe.consume("void") && probe.consume(")") {
+            self.lexer = probe;
+            (Vec::new(), false)
+        } else {
+            self.params()
+        };
+
+        // a qualifier of a parameter only matters inside the function
+        let param_tys = params.iter().map(|(_, ty, _)| ty.unqualified()).collect();
+        let mut ty = Type::func(ret_ty, param_tys);
+        ty.is_variadic = is_variadic;
+        self.last_params = params;
+        ty
+    }
+
+    // params = param ("," param)* ("," "...")? ")"
+    // param = declspec declarator
+    //
+    // A parameter of array type is a pointer to the element type, and one of
+    // function type a pointer to the function. Returns the parameters, which
+    // may be unnamed, and whether the function is variadic.
+    fn params(&mut self) -> (Vec<(Option<String>, Type, usize)>, bool) {
+        let mut params = Vec::new();
+        while !self.lexer.consume(")") {
+            if !params.is_empty() {
+                self.lexer.expect(",");
+                if self.lexer.consume("...") {
+                    self.lexer.expect(")");
+                    return (params, true);
+                }
+            }
+            let basety = self.declspec(None);
+            let (name, mut ty, loc) = self.declarator2(basety);
+            if ty.is_array() {
+                ty = Type::pointer_to(*ty.base.unwrap());
+            } else if ty.is_function() {
+                ty = Type::pointer_to(ty);
+            }
+            params.push((name, ty, loc));
+        }
+        (params, false)
+    }
+
+    // Labels are visible in the whole function, so `goto` targets can only be
+    // resolved once all of its statements are parsed. A goto frees the
+    // variable-length arrays that are not in scope at its label.
+    fn check_gotos(&mut self, body: &mut Node) {
+        let mut depths = Vec::new();
+        for (target, vlas, stmt_exprs) in self.gotos.iter() {
+            match self.labels.iter().find(|(label, _, _)| label == target) {
+                // nothing would allocate the arrays in scope at the label
+                Some((_, depth, _)) if *depth > vlas.len() => {
+                    eprintln!(
+                        "parser: jump into the scope of a variable length array to label '{}'",
+                        target
+                    );
+                    process::exit(1);
+                }
+                // the values of the statement expressions around a label
+                // would be missing if the goto is not in them too
+                Some((_, _, label_stmt_exprs)) if !stmt_exprs.starts_with(label_stmt_exprs) => {
+                    eprintln!(
+                        "parser: jump into a statement expression to label '{}'",
+                        target
+                    );
+                    process::exit(1);
+                }
+                Some((_, depth, _)) => depths.push(*depth),
+                None => {
+                    eprintln!("parser: use of undeclared label '{}'", target);
+                    process::exit(1);
+                }
+            }
+        }
+        body.visit_mut(&mut |node| {
+            if node.kind == NodeKind::NodeGoto {
+                let index = node.ival as usize;
+                node.var = self.gotos[index].1.get(depths[index]).cloned();
+            }
+        });
+    }
+
+    // compound-item = declaration | "_Static_assert" static-assert | stmt
+    fn compound_item(&mut self) -> Tree {
+        if self.lexer.consume("_Static_assert") {
+            self.static_assert();
+            return self.new_node(NodeKind::NodeBlock, None, None);
+        }
+        if self.is_typename() {
+            return self.declaration();
+        }
+        self.stmt()
+    }
+
+    fn is_typename(&self) -> bool {
+        let typenames = [
+            "char", "short", "int", "long", "float", "double", "void", "signed", "unsigned",
+            "struct", "union", "enum", "typedef", "static", "extern", "const", "volatile",
+            "restrict", "_Bool", "_Alignas",
+        ];
+        typenames.iter().any(|name| self.lexer.peek(name)) || self.peek_typedef().is_some()
+    }
+
+    // The type named by the identifier at the head, if it is a typedef name.
+    fn peek_typedef(&self) -> Option<Type> {
+        match self.find_symbol(&self.lexer.peek_ident()?) {
+            Some(Symbol::Typedef(ty)) => Some(ty.clone()),
+            _ => None,
+        }
+    }
+
+    // declspec = ("_Bool" | "char" | "short" | "int" | "long" | "float" | "double" | "void"
+    //             | "signed" | "unsigned" | "typedef" | "static" | "extern"
+    //             | "const" | "volatile" | "restrict" | "_Alignas" alignas
+    //             | "struct" struct-decl | "union" struct-decl | "enum" enum-decl
+    //             | typedef-name)+
+    //
+    // The specifiers may come in any order, so they are counted and the
+    // counts are matched against the combinations C allows. An identifier is
+    // only a typedef name if no other type specifier came before it, so that
+    // a declaration can reuse the name for a variable. Storage-class
+    // specifiers and alignment are only allowed where `attr` can record them.
+    fn declspec(&mut self, mut attr: Option<&mut VarAttr>) -> Type {
+        let loc = self.lexer.loc();
+        let (mut chars, mut shorts, mut ints, mut longs) = (0, 0, 0, 0);
+        let (mut bools, mut floats, mut doubles, mut voids) = (0, 0, 0, 0);
+        let (mut signed, mut unsigned) = (0, 0);
+        let mut is_const = false;
+        // struct, union, enum and typedef names
+        let mut named = Vec::new();
+
+        loop {
+            let specifiers = bools
+                + chars
+                + shorts
+                + ints
+                + longs
+                + floats
+                + doubles
+                + voids
+                + signed
+                + unsigned;
+            if specifiers + named.len() == 0 {
+                if let Some(ty) = self.peek_typedef() {
+                    self.lexer.is_ident_token();
+                    named.push(ty);
+                    continue;
+                }
+            }
+
+            let spec_loc = self.lexer.loc();
+            let storage_class = ["typedef", "static", "extern"]
+                .iter()
+                .find(|keyword| self.lexer.peek(keyword));
+            if let Some(keyword) = storage_class {
+                self.lexer.consume(keyword);
+                let attr = match attr.as_deref_mut() {
+                    Some(attr) => attr,
+                    None => {
+                        self.lexer
+                            .report_at(spec_loc, "storage-class specifier is not allowed here");
+                        process::exit(1);
+                    }
+                };
+                if attr.is_typedef || attr.is_static || attr.is_extern {
+                    self.lexer.report_at(
+                        spec_loc,
+                        "multiple storage classes in declaration specifiers",
+                    );
+                    process::exit(1);
+                }
+                match *keyword {
+                    "typedef" => attr.is_typedef = true,
+                    "static" => attr.is_static = true,
+                    _ => attr.is_extern = true,
+                }
+            } else if self.lexer.consume("_Alignas") {
+                let align = self.alignas();
+                match attr.as_deref_mut() {
+                    Some(attr) => attr.align = attr.align.max(align),
+                    None => {
+                        self.lexer
+                            .report_at(spec_loc, "'_Alignas' is not allowed here");
+                        process::exit(1);
+                    }
+                }
+            } else if self.lexer.consume("const") {
+                is_const = true;
+            } else if self.consume_qualifier() {
+            } else if self.lexer.consume("struct") {
+                named.push(self.struct_decl(TypeKind::TyStruct));
+            } else if self.lexer.consume("union") {
+                named.push(self.struct_decl(TypeKind::TyUnion));
+            } else if self.lexer.consume("enum") {
+                named.push(self.enum_decl());
+            } else if self.lexer.consume("_Bool") {
+                bools += 1;
+            } else if self.lexer.consume("char") {
+                chars += 1;
+            } else if self.lexer.consume("short") {
+                shorts += 1;
+            } else if self.lexer.consume("int") {
+                ints += 1;
+            } else if self.lexer.consume("long") {
+                longs += 1;
+            } else if self.lexer.consume("float") {
+                floats += 1;
+            } else if self.lexer.consume("double") {
+                doubles += 1;
+            } else if self.lexer.consume("void") {
+                voids += 1;
+            } else if self.lexer.consume("signed") {
+                signed += 1;
+            } else if self.lexer.consume("unsigned") {
+                unsigned += 1;
+            } else {
+                break;
+            }
+        }
+
+        if attr.is_some_and(|attr| attr.is_typedef && attr.align != 0) {
+            self.lexer
+                .report_at(loc, "'_Alignas' cannot be applied to a typedef");
+            process::exit(1);
+        }
+
+        let is_unsigned = unsigned > 0;
+        let others = bools + floats + doubles + voids + named.len();
+        let ty = match (chars, shorts, ints, longs, others) {
+            (0, 0, 0, 0, 1) if signed + unsigned == 0 => {
+                match (bools, floats, doubles, named.pop()) {
+                    (_, _, _, Some(ty)) => Some(ty),
+                    (1, _, _, _) => Some(Type::bool()),
+                    (_, 1, _, _) => Some(Type::float()),
+                    (_, _, 1, _) => Some(Type::double()),
+                    _ => Some(Type::void()),
+                }
+            }
+            (1, 0, 0, 0, 0) => Some(Type::char(is_unsigned)),
+            (0, 1, _, 0, 0) => Some(Type::short(is_unsigned)),
+            (0, 0, _, 0, 0) => Some(Type::int(is_unsigned)),
+            (0, 0, _, 1, 0) => Some(Type::long(is_unsigned)),
+            (0, 0, _, 2, 0) => Some(Type::long_long(is_unsigned)),
+            _ => None,
+        };
+        match ty {
+            Some(mut ty) if ints <= 1 && signed + unsigned <= 1 => {
+                ty.is_const |= is_const;
+                ty
+            }
+            _ => {
+                self.lexer
+                    .report_at(loc, "invalid combination of type specifiers");
+                process::exit(1);
+            }
+        }
+    }
+
+    // declarator = pointers ("(" declarator ")" | ident) type-suffix
+    fn declarator(&mut self, ty: Type) -> (String, Type, usize) {
+        match self.declarator2(ty) {
+            (Some(name), ty, loc) => (name, ty, loc),
+            (None, _, loc) => {
+                self.lexer.report_at(loc, "expected a variable name");
+                process::exit(1);
+            }
+        }
+    }
+
+    // A declarator whose name may be left out, as in a parameter or a type
+    // name. The suffixes after a parenthesized declarator apply to `ty`
+    // before the declarator does, so they are parsed first: `(*fp)(int)` is
+    // a pointer to a function taking an int.
+    fn declarator2(&mut self, ty: Type) -> (Option<String>, Type, usize) {
+        let ty = self.pointers(ty);
+        if self.is_nested_declarator() {
+            let start = self.lexer.clone();
+            self.lexer.expect("(");
+            self.lexer.skip_until(")");
+            let ty = self.type_suffix(ty);
+            let end = self.lexer.clone();
+
+            self.lexer = start;
+            self.lexer.expect("(");
+            let declarator = self.declarator2(ty);
+            self.lexer.expect(")");
+            self.lexer = end;
+            return declarator;
+        }
+
+        let loc = self.lexer.loc();
+        let name = self.lexer.is_ident_token();
+        (name, self.type_suffix(ty), loc)
+    }
+
+    // pointers = ("*" ("const" | "volatile" | "restrict")*)*
+    fn pointers(&mut self, mut ty: Type) -> Type {
+        while self.lexer.consume("*") {
+            ty = Type::pointer_to(ty);
+            loop {
+                if self.lexer.consume("const") {
+                    ty.is_const = true;
+                } else if !self.consume_qualifier() {
+                    break;
+                }
+            }
+        }
+        ty
+    }
+
+    // The qualifiers other than const, which do not change the code
+    // generated for an object.
+    fn consume_qualifier(&mut self) -> bool {
+        self.lexer.consume("volatile") || self.lexer.consume("restrict")
+    }
+
+    // A "(" in a declarator before its name starts a nested declarator,
+    // unless it starts the parameters of a function type without a name.
+    fn is_nested_declarator(&mut self) -> bool {
+        if !self.lexer.peek("(") {
+            return false;
+        }
+        let saved = self.lexer.clone();
+        self.lexer.expect("(");
+        let is_params = self.lexer.peek(")") || self.is_typename();
+        self.lexer = saved;
+        !is_params
+    }
+
+    // type-suffix = "(" func-params | ("[" conditional? "]")*
+    //
+    // Only the outermost dimension of an array may be left out. A dimension
+    // that is not a constant expression makes a variable-length array, whose
+    // size is computed by an assignment left in `vla_setup`.
+    fn type_suffix(&mut self, ty: Type) -> Type {
+        let loc = self.lexer.loc();
+        if self.lexer.consume("(") {
+            return self.func_params(ty, loc);
+        }
+        if !self.lexer.consume("[") {
+            return ty;
+        }
+        let loc = self.lexer.loc();
+        let len = if self.lexer.peek("]") {
+            None
+        } else {
+            let node = self.conditional();
+            if !Parser::is_const_expr(node.as_ref().unwrap()) {
+                self.lexer.expect("]");
+                return self.vla_of(ty, node, loc);
+            }
+            let len = self.eval(node.as_ref().unwrap());
+            if len < 0 {
+                self.lexer.report_at(loc, "size of array is negative");
+                process::exit(1);
+            }
+            Some(len as usize)
+        };
+        self.lexer.expect("]");
+
+        let base = self.type_suffix(ty);
+        if !base.is_complete() {
+            self.lexer
+                .report_at(loc, "array has incomplete element type");
+            process::exit(1);
+        }
+        Type::array_of(base, len)
+    }
+
+    fn vla_of(&mut self, ty: Type, len: Tree, loc: usize) -> Type {
+        if self.in_file_scope() {
+            self.lexer
+                .report_at(loc, "variable length array declared outside of a function");
+            process::exit(1);
+        }
+        let base = self.type_suffix(ty);
+        if !base.is_complete() {
+            self.lexer
+                .report_at(loc, "array has incomplete element type");
+            process::exit(1);
+        }
+        let size_var = self.new_lvar(String::new(), Type::long(true));
+        let len = self.new_cast(len, Type::long(true));
+        let base_size = self.size_of(&base);
+        let size = self.new_node(NodeKind::NodeMul, len, base_size);
+        let lhs = self.new_node_var(size_var.clone());
+        let setup = self.new_node(NodeKind::NodeAssign, lhs, size);
+        self.vla_setup.push(setup);
+        Type::vla_of(base, size_var.offset)
+    }
+
+    // Whether `node` is made of constants and operators only, so that eval
+    // can compute it.
+    fn is_const_expr(node: &Node) -> bool {
+        let is_operator = matches!(
+            node.kind,
+            NodeKind::NodeAdd
+                | NodeKind::NodeSub
+                | NodeKind::NodeMul
+                | NodeKind::NodeDiv
+                | NodeKind::NodeMod
+                | NodeKind::NodeBitAnd
+                | NodeKind::NodeBitOr
+                | NodeKind::NodeBitXor
+                | NodeKind::NodeBitNot
+                | NodeKind::NodeShl
+                | NodeKind::NodeShr
+                | NodeKind::NodeEQ
+                | NodeKind::NodeNE
+                | NodeKind::NodeLT
+                | NodeKind::NodeLE
+                | NodeKind::NodeCond
+                | NodeKind::NodeComma
+                | NodeKind::NodeCast
+        );
+        let operands = [&node.lhs, &node.rhs, &node.cond, &node.then, &node.els];
+        node.kind == NodeKind::NodeNum
+            || is_operator
+                && operands
+                    .iter()
+                    .all(|operand| operand.as_deref().is_none_or(Parser::is_const_expr))
+    }
+
+    // struct-decl = ident? ("{" (declspec member ("," member)* ";" | "_Static_assert" static-assert)* "}")?
+    // member = declarator (":" const-expr)? | ":" const-expr
+    //
+    // Parses the rest of a struct or union specifier, by `kind`. The type is
+    // visible from its own member list, so that its members can point to it.
+    // The last member of a struct may be an array of unknown length, a
+    // flexible array member.
+    fn struct_decl(&mut self, kind: TypeKind) -> Type {
+        let keyword = if kind == TypeKind::TyStruct {
+            "struct"
+        } else {
+            "union"
+        };
+        let loc = self.lexer.loc();
+        let tag = self.lexer.is_ident_token();
+        if !self.lexer.consume("{") {
+            return match tag {
+                Some(tag) => self.find_tag(tag, kind, loc),
+                None => {
+                    self.lexer
+                        .report_at(loc, &format!("expected a {} tag or '{{'", keyword));
+                    process::exit(1);
+                }
+            };
+        }
+
+        if let Some(ref tag) = tag {
+            match self.local_tag(tag, &kind, loc) {
+                Some(ty) if ty.is_complete() => {
+                    self.lexer
+                        .report_at(loc, &format!("redefinition of '{} {}'", keyword, tag));
+                    process::exit(1);
+                }
+                Some(_) => {}
+                None => {
+                    let ty = Parser::incomplete_type(&kind, Some(tag.clone()));
+                    self.scopes.last_mut().unwrap().tags.push((tag.clone(), ty));
+                }
+            }
+        }
+
+        let mut members: Vec<MemberDecl> = Vec::new();
+        // a member of incomplete type, which is only allowed last
+        let mut incomplete = None;
+        while !self.lexer.consume("}") {
+            if self.lexer.consume("_Static_assert") {
+                self.static_assert();
+                continue;
+            }
+            let spec_loc = self.lexer.loc();
+            let mut attr = VarAttr::default();
+            let basety = self.declspec(Some(&mut attr));
+            if attr.is_typedef || attr.is_static || attr.is_extern {
+                self.lexer
+                    .report_at(spec_loc, "storage-class specifier is not allowed here");
+                process::exit(1);
+            }
+            loop {
+                if let Some((name, loc)) = incomplete.take() {
+                    self.lexer
+                        .report_at(loc, &format!("'{}' has an incomplete type", name));
+                    process::exit(1);
+                }
+                let (name, mut ty, loc) = self.declarator2(basety.clone());
+                let width = if self.lexer.consume(":") {
+                    Some(self.bitfield_width(&name, &ty, loc))
+                } else {
+                    None
+                };
+                if width.is_some() && attr.align != 0 {
+                    self.lexer
+                        .report_at(loc, "'_Alignas' cannot be applied to a bit-field");
+                    process::exit(1);
+                }
+                if let Some(ref name) = name {
+                    self.align_object(&mut ty, name, loc, &attr);
+                }
+                match name {
+                    Some(ref name) => self.check_member(name, &ty, loc, &kind, &members),
+                    // only a bit-field may be unnamed, to pad the ones around it
+                    None if width.is_some() => {}
+                    None => {
+                        self.lexer.report_at(loc, "expected a member name");
+                        process::exit(1);
+                    }
+                }
+                if !ty.is_complete() {
+                    incomplete = Some((name.clone().unwrap(), loc));
+                }
+                members.push((name, ty, width));
+                if self.lexer.consume(";") {
+                    break;
+                }
+                self.lexer.expect(",");
+            }
+        }
+
+        let mut ty = Parser::incomplete_type(&kind, tag.clone());
+        ty.layout_bitfields(members);
+        if let Some(tag) = tag {
+            let scope = self.scopes.last_mut().unwrap();
+            let entry = scope
+                .tags
+                .iter_mut()
+                .find(|(name, _)| *name == tag)
+                .unwrap();
+            entry.1 = ty.clone();
+        }
+        ty
+    }
+
+    // A struct member must be complete, except for a flexible array member,
+    // and its name must be unique.
+    fn check_member(
+        &self,
+        name: &str,
+        ty: &Type,
+        loc: usize,
+        kind: &TypeKind,
+        members: &[MemberDecl],
+    ) {
+        let is_flexible = ty.is_array() && *kind == TypeKind::TyStruct && !members.is_empty();
+        if !ty.is_complete() && !is_flexible {
+            self.lexer
+                .report_at(loc, &format!("'{}' has an incomplete type", name));
+            process::exit(1);
+        }
+        if members
+            .iter()
+            .any(|(member, _, _)| member.as_deref() == Some(name))
+        {
+            self.lexer
+                .report_at(loc, &format!("duplicate member '{}'", name));
+            process::exit(1);
+        }
+    }
+
+    // The width of a bit-field, which is at most that of its integer type.
+    // Only an unnamed bit-field may have width 0.
+    fn bitfield_width(&mut self, name: &Option<String>, ty: &Type, loc: usize) -> usize {
+        let is_named = name.is_some();
+        let name = name.as_deref().unwrap_or("<anonymous>");
+        if !ty.is_integer() {
+            self.lexer
+                .report_at(loc, &format!("bit-field '{}' has invalid type", name));
+            process::exit(1);
+        }
+        let width_loc = self.lexer.loc();
+        let node = self.conditional();
+        let width = self.eval(node.as_ref().unwrap());
+        if width < 0 || width as usize > ty.size * 8 {
+            self.lexer.report_at(
+                width_loc,
+                &format!("width of bit-field '{}' exceeds its type", name),
+            );
+            process::exit(1);
+        }
+        if width == 0 && is_named {
+            self.lexer
+                .report_at(width_loc, &format!("zero width for bit-field '{}'", name));
+            process::exit(1);
+        }
+        width as usize
+    }
+
+    // enum-decl = ident? ("{" enumerator ("," enumerator)* ","? "}")?
+    // enumerator = ident ("=" const-expr)?
+    //
+    // The constants are declared in the current scope, each one greater than
+    // the previous unless given a value.
+    fn enum_decl(&mut self) -> Type {
+        let loc = self.lexer.loc();
+        let tag = self.lexer.is_ident_token();
+        if !self.lexer.consume("{") {
+            return match tag {
+                Some(tag) => self.find_tag(tag, TypeKind::TyEnum, loc),
+                None => {
+                    self.lexer.report_at(loc, "expected an enum tag or '{'");
+                    process::exit(1);
+                }
+            };
+        }
+
+        let mut val = 0;
+        let mut first = true;
+        while !self.lexer.consume("}") {
+            if !first {
+                self.lexer.expect(",");
+                if self.lexer.consume("}") {
+                    break;
+                }
+            }
+            first = false;
+
+            let loc = self.lexer.loc();
+            let name = match self.lexer.is_ident_token() {
+                Some(name) => name,
+                None => {
+                    self.lexer.report_at(loc, "expected an enumerator name");
+                    process::exit(1);
+                }
+            };
+            if self.lexer.consume("=") {
+                let node = self.conditional();
+                val = self.eval(node.as_ref().unwrap());
+            }
+            self.declare_symbol(name, Symbol::EnumConst(val), loc);
+            val += 1;
+        }
+
+        let ty = Type::enumeration();
+        if let Some(tag) = tag {
+            if self.local_tag(&tag, &TypeKind::TyEnum, loc).is_none() {
+                self.scopes.last_mut().unwrap().tags.push((tag, ty.clone()));
+            }
+        }
+        ty
+    }
+
+    fn incomplete_type(kind: &TypeKind, tag: Option<String>) -> Type {
+        match kind {
+            TypeKind::TyStruct => Type::incomplete_struct(tag),
+            TypeKind::TyUnion => Type::incomplete_union(tag),
+            _ => Type::enumeration(),
+        }
+    }
+
+    // Structs, unions and enums share one tag name space, so a tag must be
+    // used with the kind it was declared with.
+    fn check_tag_kind(&self, ty: &Type, kind: &TypeKind, tag: &str, loc: usize) {
+        if ty.kind != *kind {
+            self.lexer
+                .report_at(loc, &format!("'{}' defined as wrong kind of tag", tag));
+            process::exit(1);
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub struct LVar {
-    name: String,
-    pub offset: usize,
-}
+    // The type of a tag declared in the current scope.
+    fn local_tag(&self, tag: &str, kind: &TypeKind, loc: usize) -> Option<Type> {
+        let scope = self.scopes.last().unwrap();
+        let (_, ty) = scope.tags.iter().find(|(name, _)| name == tag)?;
+        self.check_tag_kind(ty, kind, tag, loc);
+        Some(ty.clone())
+    }
 
-#[derive(Debug, Clone)]
-pub struct Parser<'a> {
-    lexer: Tokenizer<'a>,
-    tree: Tree,
-    pub code: Vec<Tree>,
-    pub locals: Vec<LVar>,
-}
+    // Look a tag up from the innermost scope outward. An unknown tag declares
+    // an incomplete type in the current scope.
+    fn find_tag(&mut self, tag: String, kind: TypeKind, loc: usize) -> Type {
+        for scope in self.scopes.iter().rev() {
+            if let Some((_, ty)) = scope.tags.iter().find(|(name, _)| *name == tag) {
+                self.check_tag_kind(ty, &kind, &tag, loc);
+                return ty.clone();
+            }
+        }
+        let ty = Parser::incomplete_type(&kind, Some(tag.clone()));
+        self.scopes.last_mut().unwrap().tags.push((tag, ty.clone()));
+        ty
+    }
 
-impl<'a> Parser<'a> {
-    pub fn parse(lexer: Tokenizer<'a>) -> Self {
-        let mut parser = Parser {
-            lexer: lexer,
-            tree: None,
-            code: vec![None; 100],
-            locals: Vec::new(),
-        };
-        parser.program();
-        parser
+    // typedef-decl = declarator ("," declarator)* ";"
+    //
+    // The rest of a declaration whose specifiers include "typedef". Each
+    // declarator names its type rather than a variable.
+    fn typedef_decl(&mut self, basety: Type) {
+        loop {
+            let (name, ty, loc) = self.declarator(basety.clone());
+            self.declare_symbol(name, Symbol::Typedef(ty), loc);
+            if self.lexer.consume(";") {
+                return;
+            }
+            self.lexer.expect(",");
+        }
     }
 
-    fn new_node(&mut self, kind: NodeKind, lhs: Tree, rhs: Tree) -> Tree {
-        let node = Node {
-            kind: kind,
-            lhs: lhs,
-            rhs: rhs,
-            val: None,
-            offset: 0,
-            body: Vec::new(),
-            cond: None,
-            then: None,
-            els: None,
-            init: None,
-            inc: None,
+    // type-name = declspec declarator, without a name
+    fn typename(&mut self) -> Type {
+        let basety = self.declspec(None);
+        match self.declarator2(basety) {
+            (None, ty, _) => ty,
+            (Some(_), _, loc) => {
+                self.lexer.report_at(loc, "unexpected name in a type name");
+                process::exit(1);
+            }
+        }
+    }
+
+    // alignas = "(" (type-name | const-expr) ")"
+    //
+    // The alignment _Alignas asks for. 0 asks for none.
+    fn alignas(&mut self) -> usize {
+        if self.is_cast() {
+            self.lexer.expect("(");
+            let ty = self.typename();
+            self.lexer.expect(")");
+            return ty.align;
+        }
+        self.lexer.expect("(");
+        let loc = self.lexer.loc();
+        let node = self.conditional();
+        self.lexer.expect(")");
+        let align = self.eval(node.as_ref().unwrap());
+        if align < 0 || align & (align - 1) != 0 {
+            self.lexer.report_at(
+                loc,
+                &format!("requested alignment {} is not a power of 2", align),
+            );
+            process::exit(1);
+        }
+        align as usize
+    }
+
+    // Give a declared object the alignment _Alignas asked for, which may
+    // only be stricter than the one its type needs.
+    fn align_object(&self, ty: &mut Type, name: &str, loc: usize, attr: &VarAttr) {
+        if attr.align == 0 || ty.is_function() {
+            return;
+        }
+        if attr.align < ty.align {
+            self.lexer.report_at(
+                loc,
+                &format!("'_Alignas' cannot reduce the alignment of '{}'", name),
+            );
+            process::exit(1);
+        }
+        ty.align = attr.align;
+    }
+
+    // static-assert = "(" const-expr ("," str)? ")" ";"
+    fn static_assert(&mut self) {
+        self.lexer.expect("(");
+        let loc = self.lexer.loc();
+        let node = self.conditional();
+        let val = self.eval(node.as_ref().unwrap());
+        let message = if self.lexer.consume(",") {
+            match self.string_literal() {
+                Some(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+                None => {
+                    self.lexer
+                        .report_at(self.lexer.loc(), "expected a string literal");
+                    process::exit(1);
+                }
+            }
+        } else {
+            None
         };
-        Some(Box::new(node))
+        self.lexer.expect(")");
+        self.lexer.expect(";");
+        if val == 0 {
+            let msg = match message {
+                Some(message) => format!("static assertion failed: \"{}\"", message),
+                None => String::from("static assertion failed"),
+            };
+            self.lexer.report_at(loc, &msg);
+            process::exit(1);
+        }
     }
 
-    fn new_raw_node(&mut self, kind: NodeKind, lhs: Tree, rhs: Tree) -> Node {
-        let node = Node {
-            kind: kind,
-            lhs: lhs,
-            rhs: rhs,
-            val: None,
-            offset: 0,
-            body: Vec::new(),
-            cond: None,
-            then: None,
-            els: None,
-            init: None,
-            inc: None,
+    // declaration = declspec (init-declarator ("," init-declarator)*)? ";"
+    //             | "typedef" typedef-decl
+    // init-declarator = declarator ("=" initializer)?
+    fn declaration(&mut self) -> Tree {
+        let mut body: Vec<Tree> = Vec::new();
+        let mut attr = VarAttr::default();
+        let basety = self.declspec(Some(&mut attr));
+        if attr.is_typedef {
+            self.typedef_decl(basety);
+            // a variable-length array type has its size computed here
+            let mut raw_node = self.new_raw_node(NodeKind::NodeBlock, None, None);
+            raw_node.body = std::mem::take(&mut self.vla_setup);
+            return Some(Box::new(raw_node));
+        }
+        if (attr.is_static || attr.is_extern) && !self.lexer.consume(";") {
+            let (name, ty, loc) = self.declarator(basety.clone());
+            self.global_variable(basety, name, ty, loc, &attr);
+            return self.new_node(NodeKind::NodeBlock, None, None);
+        }
+
+        while !self.lexer.consume(";") {
+            let (name, mut ty, loc) = self.declarator(basety.clone());
+            self.align_object(&mut ty, &name, loc, &attr);
+            body.append(&mut self.vla_setup);
+            if ty.is_vla() {
+                if self.lexer.peek("=") {
+                    self.lexer
+                        .report_at(loc, "variable-sized object may not be initialized");
+                    process::exit(1);
+                }
+                let size = self.size_of(&ty);
+                let var = self.declare_var(name, ty, loc);
+                self.scopes.last_mut().unwrap().vlas.push(var.clone());
+                let mut raw_node = self.new_raw_node(NodeKind::NodeVla, size, None);
+                raw_node.var = Some(var);
+                body.push(Some(Box::new(raw_node)));
+            } else if self.lexer.consume("=") {
+                let (var, init) = self.declare_initialized(name, ty, loc, None);
+                self.init_local(var, &init, &mut body);
+            } else if ty.is_function() {
+                self.declare_function(name, ty, loc, &attr);
+            } else {
+                self.declare_var(name, ty, loc);
+            }
+            if self.lexer.consume(";") {
+                break;
+            }
+            self.lexer.expect(",");
+        }
+
+        let mut raw_node = self.new_raw_node(NodeKind::NodeBlock, None, None);
+        raw_node.body = body;
+        Some(Box::new(raw_node))
+    }
+
+    // Store the initializer of a local, as statements appended to `body`.
+    fn init_local(&mut self, var: LVar, init: &Initializer, body: &mut Vec<Tree>) {
+        // elements and members without an initializer are zero
+        if init.expr.is_none() && !init.children.is_empty() {
+            let mut raw_node = self.new_raw_node(NodeKind::NodeMemZero, None, None);
+            raw_node.var = Some(var.clone());
+            body.push(Some(Box::new(raw_node)));
+        }
+        let lhs = self.new_node_var(var);
+        self.init_assignments(init, lhs, body);
+    }
+
+    // initializer = string-literal | "{" initializer-list "}" | assign
+    // initializer-list = (designation? initializer ("," designation? initializer)*)? ","?
+    //
+    // Parse the initializer of a variable of type `ty`. An array of unknown
+    // length gets its length from the initializer, so the completed type is
+    // returned with it.
+    fn initializer(&mut self, ty: &Type) -> (Initializer, Type) {
+        let mut init = Initializer::new(ty, true);
+        self.initializer2(&mut init);
+        let ty = if init.is_flexible {
+            let base = *init.ty.base.clone().unwrap();
+            Type {
+                align: ty.align,
+                ..Type::array_of(base, Some(init.children.len()))
+            }
+        } else {
+            ty.clone()
         };
-        node
+        (init, ty)
     }
 
-    fn new_node_num(&self, val: String) -> Tree {
-        let node = Node {
-            kind: NodeKind::NodeNum,
-            lhs: None,
-            rhs: None,
-            val: Some(val),
-            offset: 0,
-            body: Vec::new(),
-            cond: None,
-            then: None,
-            els: None,
-            init: None,
-            inc: None,
+    fn initializer2(&mut self, init: &mut Initializer) {
+        // only a flexible array member has no length by now
+        if init.ty.is_array() && init.ty.array_len.is_none() && !init.is_flexible {
+            self.lexer.report_at(
+                self.lexer.loc(),
+                "initialization of a flexible array member is not supported",
+            );
+            process::exit(1);
+        }
+        if init.ty.is_array() {
+            let is_char = init.ty.base.as_ref().unwrap().kind == TypeKind::TyChar;
+            if is_char && self.string_initializer(init) {
+                return;
+            }
+            if self.lexer.consume("{") {
+                self.array_initializer1(init);
+            } else {
+                self.array_initializer2(init);
+            }
+            return;
+        }
+
+        if init.ty.is_struct() {
+            if self.lexer.consume("{") {
+                self.struct_initializer1(init);
+                return;
+            }
+            // an expression of the struct type, or else the members without
+            // braces
+            let saved = self.lexer.clone();
+            let expr = self.assign();
+            if Parser::ty_of(&expr).is_struct() {
+                init.expr = expr;
+                return;
+            }
+            self.lexer = saved;
+            self.struct_initializer2(init);
+            return;
+        }
+
+        // a scalar may have braces around its initializer
+        if self.lexer.consume("{") {
+            self.initializer2(init);
+            if !self.consume_end() {
+                self.lexer.expect("}");
+            }
+            return;
+        }
+        init.expr = self.assign();
+    }
+
+    // A char array can be initialized by a string literal, which may be in
+    // braces. The terminating NUL is left out if the array is too short for it.
+    fn string_initializer(&mut self, init: &mut Initializer) -> bool {
+        let saved = self.lexer.clone();
+        let braced = self.lexer.consume("{");
+        let mut bytes = match self.string_literal() {
+            Some(bytes) => bytes,
+            None => {
+                self.lexer = saved;
+                return false;
+            }
         };
-        Some(Box::new(node))
+        if braced && !self.consume_end() {
+            self.lexer = saved;
+            return false;
+        }
+
+        bytes.push(0);
+        init.grow_to(bytes.len() - 1);
+        for (child, byte) in init.children.iter_mut().zip(bytes) {
+            child.expr = self.new_node_num(byte as i8 as i64);
+        }
+        true
     }
 
-    pub fn find_var(&mut self, val: String) -> usize {
-        let mut locals = self.locals.clone();
-        loop {
-            if let Some(local) = locals.pop() {
-                if local.name == val {
-                    return local.offset;
+    // The elements of an array, in braces.
+    fn array_initializer1(&mut self, init: &mut Initializer) {
+        let mut i = 0;
+        let mut first = true;
+        while !self.consume_end() {
+            if !first {
+                self.lexer.expect(",");
+            }
+            first = false;
+
+            if self.lexer.consume("[") {
+                i = self.array_designator(init);
+                self.designation(&mut init.children[i]);
+                i += 1;
+                continue;
+            }
+            init.grow_to(i);
+            match init.children.get_mut(i) {
+                Some(child) => self.initializer2(child),
+                None => self.skip_excess_element(),
+            }
+            i += 1;
+        }
+    }
+
+    // The elements of an array nested in another initializer without braces
+    // of its own. It takes only as many elements as it has, leaving the rest
+    // to the enclosing initializer.
+    fn array_initializer2(&mut self, init: &mut Initializer) {
+        let mut i = 0;
+        while (init.is_flexible || i < init.children.len()) && !self.is_end() {
+            if i > 0 {
+                if self.next_is_designator() {
+                    return;
                 }
-            } else {
-                let offset = (self.locals.len() + 1) * 8;
-                let new_var = LVar {
-                    name: val,
-                    offset: offset,
-                };
-                self.locals.push(new_var.clone());
-                return offset;
+                self.lexer.expect(",");
             }
+            init.grow_to(i);
+            self.initializer2(&mut init.children[i]);
+            i += 1;
         }
     }
 
-    // program = stmt*
-    fn program(&mut self) {
+    // The members of a struct, in braces. A union takes one initializer for
+    // its first member unless another one is designated.
+    fn struct_initializer1(&mut self, init: &mut Initializer) {
         let mut i = 0;
-        while !self.lexer.at_eof() {
-            self.code[i] = self.stmt();
+        let mut first = true;
+        while !self.consume_end() {
+            if !first {
+                self.lexer.expect(",");
+            }
+            first = false;
+
+            if self.lexer.consume(".") {
+                i = self.struct_designator(init);
+                self.designation(&mut init.children[i]);
+                i += 1;
+                continue;
+            }
+            if init.ty.kind == TypeKind::TyUnion && i > 0 {
+                self.skip_excess_element();
+                continue;
+            }
+            match init.children.get_mut(i) {
+                Some(child) => {
+                    self.initializer2(child);
+                    if init.ty.kind == TypeKind::TyUnion {
+                        init.union_member = Some(i);
+                    }
+                }
+                None => self.skip_excess_element(),
+            }
+            i += 1;
+        }
+    }
+
+    // The members of a struct nested in another initializer without braces
+    // of its own.
+    fn struct_initializer2(&mut self, init: &mut Initializer) {
+        let len = if init.ty.kind == TypeKind::TyUnion {
+            init.union_member = Some(0);
+            1
+        } else {
+            init.children.len()
+        };
+        let mut i = 0;
+        while i < len && !self.is_end() {
+            if i > 0 {
+                if self.next_is_designator() {
+                    return;
+                }
+                self.lexer.expect(",");
+            }
+            self.initializer2(&mut init.children[i]);
             i += 1;
         }
     }
 
+    // designation = ("[" const-expr "]" | "." ident)* "="
+    //
+    // Parse the rest of a designation, whose first designator is consumed,
+    // and the initializer of the designated element or member.
+    fn designation(&mut self, init: &mut Initializer) {
+        if init.ty.is_array() && self.lexer.consume("[") {
+            let i = self.array_designator(init);
+            self.designation(&mut init.children[i]);
+            return;
+        }
+        if init.ty.is_struct() && self.lexer.consume(".") {
+            let i = self.struct_designator(init);
+            self.designation(&mut init.children[i]);
+            return;
+        }
+        self.lexer.expect("=");
+        self.initializer2(init);
+    }
+
+    // array-designator = "[" const-expr "]", with the "[" consumed
+    fn array_designator(&mut self, init: &mut Initializer) -> usize {
+        let loc = self.lexer.loc();
+        let node = self.conditional();
+        let index = self.eval(node.as_ref().unwrap());
+        self.lexer.expect("]");
+        if index < 0 || (!init.is_flexible && index as usize >= init.children.len()) {
+            self.lexer
+                .report_at(loc, "array index in initializer exceeds array bounds");
+            process::exit(1);
+        }
+        init.grow_to(index as usize);
+        index as usize
+    }
+
+    // struct-designator = "." ident, with the "." consumed
+    fn struct_designator(&mut self, init: &mut Initializer) -> usize {
+        let loc = self.lexer.loc();
+        let name = self.lexer.is_ident_token();
+        let members = init.ty.members.as_ref().unwrap();
+        match members
+            .iter()
+            .position(|member| Some(&member.name) == name.as_ref())
+        {
+            Some(i) => {
+                if init.ty.kind == TypeKind::TyUnion {
+                    init.union_member = Some(i);
+                }
+                i
+            }
+            None => {
+                self.lexer.report_at(loc, "expected a member name");
+                process::exit(1);
+            }
+        }
+    }
+
+    // Elements beyond the end of an array or struct are parsed and dropped.
+    fn skip_excess_element(&mut self) {
+        if self.lexer.consume("{") {
+            self.lexer.skip_until("}");
+        } else {
+            self.assign();
+        }
+    }
+
+    // The end of an initializer list, which may have a trailing comma.
+    fn is_end(&self) -> bool {
+        let mut probe = self.lexer.clone();
+        probe.consume("}") || (probe.consume(",") && probe.consume("}"))
+    }
+
+    fn consume_end(&mut self) -> bool {
+        if !self.is_end() {
+            return false;
+        }
+        self.lexer.consume(",");
+        self.lexer.expect("}");
+        true
+    }
+
+    // Whether the next initializer in the list starts with a designator,
+    // which names its own position.
+    fn next_is_designator(&self) -> bool {
+        let mut probe = self.lexer.clone();
+        probe.consume(",") && (probe.peek("[") || probe.peek("."))
+    }
+
+    // Assignments that store the initializer of a local variable to `lhs`.
+    fn init_assignments(&mut self, init: &Initializer, lhs: Tree, body: &mut Vec<Tree>) {
+        if init.ty.is_array() {
+            for (i, child) in init.children.iter().enumerate() {
+                let index = self.new_node_num(i as i64);
+                let addr = self.new_add(lhs.clone(), index);
+                let elem = self.new_node(NodeKind::NodeDeref, addr, None);
+                self.init_assignments(child, elem, body);
+            }
+            return;
+        }
+        if init.ty.is_struct() && init.expr.is_none() {
+            let members = init.ty.members.clone().unwrap();
+            for (i, (child, member)) in init.children.iter().zip(members).enumerate() {
+                if init.ty.kind == TypeKind::TyUnion && init.union_member != Some(i) {
+                    continue;
+                }
+                let node = self.new_member(lhs.clone(), member);
+                self.init_assignments(child, node, body);
+            }
+            return;
+        }
+        if let Some(ref expr) = init.expr {
+            body.push(self.new_node(NodeKind::NodeAssign, lhs, Some(expr.clone())));
+        }
+    }
+
+    // Write the initial contents of a global variable at `offset` in `data`.
+    // Addresses of other globals are left to the linker as relocations.
+    fn write_global_data(
+        &self,
+        init: &Initializer,
+        data: &mut [u8],
+        offset: usize,
+        relocations: &mut Vec<Relocation>,
+    ) {
+        let ty = &init.ty;
+        if ty.is_array() {
+            let size = ty.base.as_ref().unwrap().size;
+            for (i, child) in init.children.iter().enumerate() {
+                self.write_global_data(child, data, offset + i * size, relocations);
+            }
+            return;
+        }
+        if ty.is_struct() && init.expr.is_none() {
+            let members = ty.members.as_ref().unwrap();
+            for (i, (child, member)) in init.children.iter().zip(members).enumerate() {
+                if ty.kind == TypeKind::TyUnion && init.union_member != Some(i) {
+                    continue;
+                }
+                if member.is_bitfield {
+                    self.write_bitfield(child, data, offset + member.offset, member);
+                    continue;
+                }
+                self.write_global_data(child, data, offset + member.offset, relocations);
+            }
+            return;
+        }
+        let expr = match init.expr {
+            Some(ref expr) => expr,
+            None => return,
+        };
+        if ty.is_struct() {
+            eprintln!("parser: initializer element is not a compile-time constant");
+            process::exit(1);
+        }
+
+        let bytes = if ty.kind == TypeKind::TyBool {
+            // converted like an assignment, so that it holds 0 or 1
+            let val = self.new_cast(Some(expr.clone()), ty.clone());
+            vec![self.eval(val.as_ref().unwrap()) as u8]
+        } else if ty.kind == TypeKind::TyFloat {
+            (self.eval_double(expr) as f32)
+                .to_bits()
+                .to_le_bytes()
+                .to_vec()
+        } else if ty.kind == TypeKind::TyDouble {
+            self.eval_double(expr).to_bits().to_le_bytes().to_vec()
+        } else {
+            let (label, val) = self.eval_address(expr);
+            if let Some(label) = label {
+                if ty.size != 8 {
+                    eprintln!("parser: initializer element is not a compile-time constant");
+                    process::exit(1);
+                }
+                relocations.push(Relocation {
+                    offset,
+                    label,
+                    addend: val,
+                });
+                return;
+            }
+            val.to_le_bytes()[..ty.size].to_vec()
+        };
+        data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    // A bit-field shares its storage unit with its neighbours, so its bits
+    // are merged into the unit.
+    fn write_bitfield(&self, init: &Initializer, data: &mut [u8], offset: usize, member: &Member) {
+        let expr = match init.expr {
+            Some(ref expr) => expr,
+            None => return,
+        };
+        let val = self.new_cast(Some(expr.clone()), member.ty.clone());
+        let val = self.eval(val.as_ref().unwrap()) as u64;
+        let unit = &mut data[offset..offset + member.ty.size];
+        let mut bytes = [0; 8];
+        bytes[..unit.len()].copy_from_slice(unit);
+        let mask = member.bit_mask() << member.bit_offset;
+        let merged = u64::from_le_bytes(bytes) & !mask | (val << member.bit_offset) & mask;
+        unit.copy_from_slice(&merged.to_le_bytes()[..unit.len()]);
+    }
+
     // stmt = expr? ";"
     //      | "return" expr ";"
     //      | "if" "(" expr ")" stmt ("else" stmt)?
-    //      | "for" "(" expr? ";" expr? ";" expr? ")" stmt
-    //      | "while" "(" expr ")" strmt
+    //      | "for" "(" (declaration | expr? ";") expr? ";" expr? ")" stmt
+    //      | "while" "(" expr ")" stmt
+    //      | "do" stmt "while" "(" expr ")" ";"
+    //      | "break" ";"
+    //      | "continue" ";"
+    //      | "goto" ident ";"
+    //      | "switch" "(" expr ")" stmt
+    //      | "case" const-expr ":" stmt
+    //      | "default" ":" stmt
+    //      | ident ":" stmt
     //      | "{" block
     fn stmt(&mut self) -> Tree {
         let node: Tree;
@@ -160,7 +2538,11 @@ impl<'a> Parser<'a> {
         }
 
         if self.lexer.consume("return") {
-            let lhs = self.expr();
+            if self.lexer.consume(";") {
+                return self.new_node(NodeKind::NodeReturn, None, None);
+            }
+            let expr = self.expr();
+            let lhs = self.new_cast(expr, self.ret_ty.clone());
             node = self.new_node(NodeKind::NodeReturn, lhs, None);
             self.lexer.expect(";");
             return node;
@@ -181,7 +2563,10 @@ impl<'a> Parser<'a> {
         if self.lexer.consume("for") {
             let mut raw_node = self.new_raw_node(NodeKind::NodeFor, None, None);
             self.lexer.expect("(");
-            if !self.lexer.consume(";") {
+            self.enter_scope();
+            if self.is_typename() {
+                raw_node.init = self.declaration();
+            } else if !self.lexer.consume(";") {
                 raw_node.init = self.expr();
                 self.lexer.expect(";");
             }
@@ -193,16 +2578,161 @@ impl<'a> Parser<'a> {
                 raw_node.inc = self.expr();
                 self.lexer.expect(")");
             }
-            raw_node.then = self.stmt();
-            return Some(Box::new(raw_node));
+            raw_node.then = self.loop_body();
+            self.leave_scope();
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("while") {
+            let mut raw_node = self.new_raw_node(NodeKind::NodeFor, None, None);
+            self.lexer.expect("(");
+            raw_node.cond = self.expr();
+            self.lexer.expect(")");
+            raw_node.then = self.loop_body();
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("do") {
+            let mut raw_node = self.new_raw_node(NodeKind::NodeDo, None, None);
+            raw_node.then = self.loop_body();
+            if !self.lexer.consume("while") {
+                eprintln!("parser: expected 'while' after do statement");
+                process::exit(1);
+            }
+            self.lexer.expect("(");
+            raw_node.cond = self.expr();
+            self.lexer.expect(")");
+            self.lexer.expect(";");
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("break") {
+            self.lexer.expect(";");
+            let mut raw_node = self.new_raw_node(NodeKind::NodeBreak, None, None);
+            raw_node.var = self.vlas_left(self.break_vlas.last().copied());
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("continue") {
+            self.lexer.expect(";");
+            let mut raw_node = self.new_raw_node(NodeKind::NodeContinue, None, None);
+            raw_node.var = self.vlas_left(self.continue_vlas.last().copied());
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("goto") {
+            let target = match self.lexer.is_ident_token() {
+                Some(target) => target,
+                None => {
+                    eprintln!("parser: expected a label name after goto");
+                    process::exit(1);
+                }
+            };
+            self.lexer.expect(";");
+            let mut raw_node = self.new_raw_node(NodeKind::NodeGoto, None, None);
+            raw_node.val = Some(target.clone());
+            raw_node.ival = self.gotos.len() as i64;
+            self.gotos
+                .push((target, self.vla_chain(), self.stmt_exprs.clone()));
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("switch") {
+            let mut raw_node = self.new_raw_node(NodeKind::NodeSwitch, None, None);
+            self.lexer.expect("(");
+            let loc = self.lexer.loc();
+            let cond = self.expr();
+            let ty = Parser::ty_of(&cond).promote();
+            if !ty.is_integer() {
+                self.lexer
+                    .report_at(loc, "switch quantity is not an integer");
+                process::exit(1);
+            }
+            raw_node.cond = self.new_cast(cond, ty.clone());
+            self.lexer.expect(")");
+
+            self.switches.push(SwitchScope {
+                ty,
+                cases: Vec::new(),
+                default_loc: None,
+            });
+            self.break_vlas.push(self.vla_chain().len());
+            raw_node.then = self.stmt();
+            self.break_vlas.pop();
+            let scope = self.switches.pop().unwrap();
+            raw_node.cases = scope.cases.iter().map(|(val, _)| *val).collect();
+            raw_node.has_default = scope.default_loc.is_some();
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("case") {
+            let loc = self.lexer.loc();
+            let expr = self.conditional();
+            let val = self.eval(&expr.unwrap());
+            self.lexer.expect(":");
+
+            let scope = match self.switches.last_mut() {
+                Some(scope) => scope,
+                None => {
+                    self.lexer
+                        .report_at(loc, "case label not within a switch statement");
+                    process::exit(1);
+                }
+            };
+            let val = scope.ty.truncate(val);
+            if let Some((_, prev)) = scope.cases.iter().find(|(v, _)| *v == val) {
+                let prev = *prev;
+                self.lexer
+                    .report_at(loc, &format!("duplicate case value '{}'", val));
+                self.lexer.report_at(prev, "previously used here");
+                process::exit(1);
+            }
+            scope.cases.push((val, loc));
+            let case_index = scope.cases.len() - 1;
+
+            let lhs = self.stmt();
+            let mut raw_node = self.new_raw_node(NodeKind::NodeCase, lhs, None);
+            raw_node.case_index = Some(case_index);
+            return Some(Box::new(raw_node));
+        }
+
+        if self.lexer.consume("default") {
+            let loc = self.lexer.loc();
+            self.lexer.expect(":");
+
+            let scope = match self.switches.last_mut() {
+                Some(scope) => scope,
+                None => {
+                    self.lexer
+                        .report_at(loc, "default label not within a switch statement");
+                    process::exit(1);
+                }
+            };
+            if let Some(prev) = scope.default_loc {
+                self.lexer
+                    .report_at(loc, "multiple default labels in one switch");
+                self.lexer.report_at(prev, "previously used here");
+                process::exit(1);
+            }
+            scope.default_loc = Some(loc);
+
+            let lhs = self.stmt();
+            return self.new_node(NodeKind::NodeCase, lhs, None);
         }
 
-        if self.lexer.consume("while") {
-            let mut raw_node = self.new_raw_node(NodeKind::NodeFor, None, None);
-            self.lexer.expect("(");
-            raw_node.cond = self.expr();
-            self.lexer.expect(")");
-            raw_node.then = self.stmt();
+        if let Some(label) = self.lexer.is_label_token() {
+            if self.labels.iter().any(|(prev, _, _)| *prev == label) {
+                eprintln!("parser: duplicate label '{}'", label);
+                process::exit(1);
+            }
+            self.labels.push((
+                label.clone(),
+                self.vla_chain().len(),
+                self.stmt_exprs.clone(),
+            ));
+            let lhs = self.stmt();
+            let mut raw_node = self.new_raw_node(NodeKind::NodeLabel, lhs, None);
+            raw_node.val = Some(label);
             return Some(Box::new(raw_node));
         }
 
@@ -212,41 +2742,339 @@ impl<'a> Parser<'a> {
         node = self.expr();
         self.lexer.expect(";");
 
-        return node;
+        node
+    }
+
+    // The body of a loop, which break and continue leave.
+    fn loop_body(&mut self) -> Tree {
+        let depth = self.vla_chain().len();
+        self.break_vlas.push(depth);
+        self.continue_vlas.push(depth);
+        let body = self.stmt();
+        self.break_vlas.pop();
+        self.continue_vlas.pop();
+        body
     }
 
-    // block = stmt* "}"
+    // block = compound-item* "}"
     fn block(&mut self) -> Tree {
         let mut node = self.new_node(NodeKind::NodeBlock, None, None);
         let mut body: Vec<Tree> = Vec::new();
 
+        self.enter_scope();
         while !self.lexer.consume("}") {
-            body.push(self.stmt());
+            body.push(self.compound_item());
         }
+        let vla = self.scopes.last().unwrap().vlas.first().cloned();
+        self.leave_scope();
 
         if let Some(node_ref) = node.as_mut() {
-            node_ref.body = body
+            node_ref.body = body;
+            // the arrays declared in the block are freed at its end
+            node_ref.var = vla;
         } else {
             eprintln!("parser: expected node, but not found");
             process::exit(1);
         }
 
-        return node;
+        node
+    }
+
+    // Evaluate a constant expression such as a case label. The result is
+    // truncated to the type of the expression.
+    fn eval(&self, node: &Node) -> i64 {
+        if node.ty.as_ref().is_some_and(|ty| ty.is_flonum()) {
+            eprintln!("parser: expected an integer constant expression");
+            process::exit(1);
+        }
+        let val = self.eval_untyped(node);
+        match node.ty {
+            Some(ref ty) if ty.is_integer() => ty.truncate(val),
+            _ => val,
+        }
+    }
+
+    fn eval_untyped(&self, node: &Node) -> i64 {
+        let lhs = || self.eval(node.lhs.as_ref().unwrap());
+        let rhs = || self.eval(node.rhs.as_ref().unwrap());
+        // operands were converted to a common type, so either one tells
+        // whether the operation is unsigned
+        let is_unsigned = node
+            .lhs
+            .as_ref()
+            .and_then(|lhs| lhs.ty.as_ref())
+            .is_some_and(|ty| ty.is_unsigned);
+
+        match node.kind {
+            NodeKind::NodeNum => node.ival,
+            NodeKind::NodeAdd => lhs().wrapping_add(rhs()),
+            NodeKind::NodeSub => lhs().wrapping_sub(rhs()),
+            NodeKind::NodeMul => lhs().wrapping_mul(rhs()),
+            NodeKind::NodeDiv | NodeKind::NodeMod => {
+                let divisor = rhs();
+                if divisor == 0 {
+                    eprintln!("parser: division by zero in constant expression");
+                    process::exit(1);
+                }
+                match (&node.kind, is_unsigned) {
+                    (NodeKind::NodeDiv, false) => lhs().wrapping_div(divisor),
+                    (NodeKind::NodeDiv, true) => (lhs() as u64 / divisor as u64) as i64,
+                    (_, false) => lhs().wrapping_rem(divisor),
+                    (_, true) => (lhs() as u64 % divisor as u64) as i64,
+                }
+            }
+            NodeKind::NodeBitAnd => lhs() & rhs(),
+            NodeKind::NodeBitOr => lhs() | rhs(),
+            NodeKind::NodeBitXor => lhs() ^ rhs(),
+            NodeKind::NodeBitNot => !lhs(),
+            NodeKind::NodeShl => lhs().wrapping_shl(rhs() as u32),
+            NodeKind::NodeShr if is_unsigned => (lhs() as u64).wrapping_shr(rhs() as u32) as i64,
+            NodeKind::NodeShr => lhs().wrapping_shr(rhs() as u32),
+            NodeKind::NodeEQ => (lhs() == rhs()) as i64,
+            NodeKind::NodeNE => (lhs() != rhs()) as i64,
+            NodeKind::NodeLT if is_unsigned => ((lhs() as u64) < rhs() as u64) as i64,
+            NodeKind::NodeLT => (lhs() < rhs()) as i64,
+            NodeKind::NodeLE if is_unsigned => (lhs() as u64 <= rhs() as u64) as i64,
+            NodeKind::NodeLE => (lhs() <= rhs()) as i64,
+            NodeKind::NodeCast => {
+                let from = node.lhs.as_ref().unwrap();
+                if Parser::ty_of(&node.lhs).is_flonum() {
+                    let val = self.eval_double(from);
+                    if node.ty.as_ref().unwrap().kind == TypeKind::TyBool {
+                        return (val != 0.0) as i64;
+                    }
+                    if node.ty.as_ref().unwrap().is_unsigned {
+                        return val as u64 as i64;
+                    }
+                    return val as i64;
+                }
+                lhs()
+            }
+            NodeKind::NodeComma => rhs(),
+            NodeKind::NodeCond => {
+                if self.eval(node.cond.as_ref().unwrap()) != 0 {
+                    self.eval(node.then.as_ref().unwrap())
+                } else {
+                    self.eval(node.els.as_ref().unwrap())
+                }
+            }
+            _ => {
+                eprintln!("parser: not a constant expression");
+                process::exit(1);
+            }
+        }
+    }
+
+    // Evaluate a constant expression of floating-point type.
+    fn eval_double(&self, node: &Node) -> f64 {
+        let lhs = || self.eval_double(node.lhs.as_ref().unwrap());
+        let rhs = || self.eval_double(node.rhs.as_ref().unwrap());
+        let ty = node.ty.as_ref().unwrap();
+
+        match node.kind {
+            NodeKind::NodeNum if ty.is_flonum() => node.fval,
+            NodeKind::NodeNum if ty.is_unsigned => node.ival as u64 as f64,
+            NodeKind::NodeNum => node.ival as f64,
+            NodeKind::NodeCast => {
+                let from = node.lhs.as_ref().unwrap();
+                let val = if Parser::ty_of(&node.lhs).is_flonum() {
+                    lhs()
+                } else if Parser::ty_of(&node.lhs).is_unsigned {
+                    self.eval(from) as u64 as f64
+                } else {
+                    self.eval(from) as f64
+                };
+                match ty.kind {
+                    TypeKind::TyFloat => val as f32 as f64,
+                    _ => val,
+                }
+            }
+            NodeKind::NodeAdd => lhs() + rhs(),
+            NodeKind::NodeSub => lhs() - rhs(),
+            NodeKind::NodeMul => lhs() * rhs(),
+            NodeKind::NodeDiv => lhs() / rhs(),
+            NodeKind::NodeComma => rhs(),
+            NodeKind::NodeCond => {
+                if self.eval(node.cond.as_ref().unwrap()) != 0 {
+                    self.eval_double(node.then.as_ref().unwrap())
+                } else {
+                    self.eval_double(node.els.as_ref().unwrap())
+                }
+            }
+            _ if ty.is_integer() => self.eval(node) as f64,
+            _ => {
+                eprintln!("parser: not a constant expression");
+                process::exit(1);
+            }
+        }
+    }
+
+    // Evaluate the initializer of a global scalar, which may be the address
+    // of a global variable plus a constant. The address is returned as the
+    // name of the variable, for the linker to resolve.
+    fn eval_address(&self, node: &Node) -> (Option<String>, i64) {
+        // an array is the address of its first element, and a function is
+        // its own address
+        if Parser::ty_of_node(node).is_array() || Parser::ty_of_node(node).is_function() {
+            return self.eval_lval_address(node);
+        }
+        match node.kind {
+            NodeKind::NodeAddr => self.eval_lval_address(node.lhs.as_ref().unwrap()),
+            NodeKind::NodeCast => self.eval_address(node.lhs.as_ref().unwrap()),
+            NodeKind::NodeAdd | NodeKind::NodeSub if Parser::ty_of_node(node).is_pointer() => {
+                let (label, val) = self.eval_address(node.lhs.as_ref().unwrap());
+                let offset = self.eval(node.rhs.as_ref().unwrap());
+                if node.kind == NodeKind::NodeAdd {
+                    (label, val.wrapping_add(offset))
+                } else {
+                    (label, val.wrapping_sub(offset))
+                }
+            }
+            _ => (None, self.eval(node)),
+        }
+    }
+
+    fn eval_lval_address(&self, node: &Node) -> (Option<String>, i64) {
+        match node.kind {
+            NodeKind::NodeLVar if node.var.as_ref().unwrap().is_global => {
+                (Some(node.var.as_ref().unwrap().name.clone()), 0)
+            }
+            NodeKind::NodeMember => {
+                let (label, val) = self.eval_lval_address(node.lhs.as_ref().unwrap());
+                (label, val + node.member.as_ref().unwrap().offset as i64)
+            }
+            NodeKind::NodeDeref => self.eval_address(node.lhs.as_ref().unwrap()),
+            _ => {
+                eprintln!("parser: initializer element is not a compile-time constant");
+                process::exit(1);
+            }
+        }
     }
 
-    // expr = assign
+    // expr = assign ("," assign)*
     fn expr(&mut self) -> Tree {
-        return self.assign();
+        let mut node = self.assign();
+        while self.lexer.consume(",") {
+            let rhs = self.assign();
+            node = self.new_node(NodeKind::NodeComma, node, rhs);
+        }
+        node
     }
 
-    // assign = equality ("=" assign)?
+    // assign = conditional (assign-op assign)?
+    // assign-op = "=" | "+=" | "-=" | "*=" | "/=" | "%=" | "&=" | "|=" | "^=" | "<<=" | ">>="
     fn assign(&mut self) -> Tree {
-        let mut node = self.equality();
+        let loc = self.lexer.loc();
+        let mut node = self.conditional();
         if self.lexer.consume("=") {
+            self.check_assignable(&node, loc);
             let rhs = self.assign();
             node = self.new_node(NodeKind::NodeAssign, node, rhs);
+            return node;
+        }
+
+        let compound_ops = [
+            ("+=", NodeKind::NodeAdd),
+            ("-=", NodeKind::NodeSub),
+            ("*=", NodeKind::NodeMul),
+            ("/=", NodeKind::NodeDiv),
+            ("%=", NodeKind::NodeMod),
+            ("&=", NodeKind::NodeBitAnd),
+            ("|=", NodeKind::NodeBitOr),
+            ("^=", NodeKind::NodeBitXor),
+            ("<<=", NodeKind::NodeShl),
+            (">>=", NodeKind::NodeShr),
+        ];
+        for (op, kind) in compound_ops.iter() {
+            if self.lexer.consume(op) {
+                self.check_assignable(&node, loc);
+                let value = self.assign();
+                node = self.new_assign_op(node, kind.clone(), value);
+                break;
+            }
         }
-        return node;
+        node
+    }
+
+    // Whether an expression designates an object, which is what gen_lval
+    // can compute the address of.
+    fn is_lvalue(node: &Node) -> bool {
+        match node.kind {
+            NodeKind::NodeLVar | NodeKind::NodeDeref | NodeKind::NodeCompoundLiteral => true,
+            NodeKind::NodeMember => Parser::is_lvalue(node.lhs.as_ref().unwrap()),
+            _ => false,
+        }
+    }
+
+    // An object declared const may only be given a value by its initializer,
+    // and so may a struct or union with a const member.
+    fn check_assignable(&self, lhs: &Tree, loc: usize) {
+        if !Parser::is_lvalue(lhs.as_ref().unwrap()) {
+            self.lexer.report_at(loc, "expression is not assignable");
+            process::exit(1);
+        }
+        let ty = Parser::ty_of(lhs);
+        if ty.is_const || ty.has_readonly_member() {
+            self.lexer
+                .report_at(loc, "cannot assign to a read-only location");
+            process::exit(1);
+        }
+    }
+
+    // `lhs op= value` stores `lhs op value` converted back to the type of lhs.
+    fn new_assign_op(&mut self, lhs: Tree, kind: NodeKind, value: Tree) -> Tree {
+        let ty = Parser::ty_of(&lhs);
+        let mut old = self.new_raw_node(NodeKind::NodeOldValue, None, None);
+        old.ty = Some(ty.clone());
+        let result = self.new_binary(kind, Some(Box::new(old)), value);
+        let rhs = self.new_cast(result, ty);
+        self.new_node(NodeKind::NodeAssignOp, lhs, rhs)
+    }
+
+    // conditional = bitor ("?" expr ":" conditional)?
+    fn conditional(&mut self) -> Tree {
+        let cond = self.bitor();
+        if !self.lexer.consume("?") {
+            return cond;
+        }
+
+        let mut raw_node = self.new_raw_node(NodeKind::NodeCond, None, None);
+        raw_node.cond = cond;
+        raw_node.then = self.expr();
+        self.lexer.expect(":");
+        raw_node.els = self.conditional();
+        self.add_type(&mut raw_node);
+        Some(Box::new(raw_node))
+    }
+
+    // bitor = bitxor ("|" bitxor)*
+    fn bitor(&mut self) -> Tree {
+        let mut node = self.bitxor();
+        while self.lexer.consume("|") {
+            let rhs = self.bitxor();
+            node = self.new_node(NodeKind::NodeBitOr, node, rhs);
+        }
+        node
+    }
+
+    // bitxor = bitand ("^" bitand)*
+    fn bitxor(&mut self) -> Tree {
+        let mut node = self.bitand();
+        while self.lexer.consume("^") {
+            let rhs = self.bitand();
+            node = self.new_node(NodeKind::NodeBitXor, node, rhs);
+        }
+        node
+    }
+
+    // bitand = equality ("&" equality)*
+    fn bitand(&mut self) -> Tree {
+        let mut node = self.equality();
+        while self.lexer.consume("&") {
+            let rhs = self.equality();
+            node = self.new_node(NodeKind::NodeBitAnd, node, rhs);
+        }
+        node
     }
 
     // equality = relational ("==" relational | "!=" relational)*
@@ -266,22 +3094,22 @@ impl<'a> Parser<'a> {
         }
     }
 
-    // relational = add ("<" add | "<=" add | ">" add | ">=" add)*
+    // relational = shift ("<" shift | "<=" shift | ">" shift | ">=" shift)*
     fn relational(&mut self) -> Tree {
-        let mut node = self.add();
+        let mut node = self.shift();
 
         loop {
             if self.lexer.consume("<") {
-                let rhs = self.add();
+                let rhs = self.shift();
                 node = self.new_node(NodeKind::NodeLT, node, rhs);
             } else if self.lexer.consume("<=") {
-                let rhs = self.add();
+                let rhs = self.shift();
                 node = self.new_node(NodeKind::NodeLE, node, rhs);
             } else if self.lexer.consume(">") {
-                let lhs = self.add();
+                let lhs = self.shift();
                 node = self.new_node(NodeKind::NodeLT, lhs, node);
             } else if self.lexer.consume(">=") {
-                let lhs = self.add();
+                let lhs = self.shift();
                 node = self.new_node(NodeKind::NodeLE, lhs, node);
             } else {
                 return node;
@@ -289,6 +3117,23 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // shift = add ("<<" add | ">>" add)*
+    fn shift(&mut self) -> Tree {
+        let mut node = self.add();
+
+        loop {
+            if self.lexer.consume("<<") {
+                let rhs = self.add();
+                node = self.new_node(NodeKind::NodeShl, node, rhs);
+            } else if self.lexer.consume(">>") {
+                let rhs = self.add();
+                node = self.new_node(NodeKind::NodeShr, node, rhs);
+            } else {
+                return node;
+            }
+        }
+    }
+
     // add = mul ("+" mul | "-" mul)*
     fn add(&mut self) -> Tree {
         let mut node = self.mul();
@@ -296,17 +3141,17 @@ impl<'a> Parser<'a> {
         loop {
             if self.lexer.consume("+") {
                 let rhs = self.mul();
-                node = self.new_node(NodeKind::NodeAdd, node, rhs);
+                node = self.new_add(node, rhs);
             } else if self.lexer.consume("-") {
                 let rhs = self.mul();
-                node = self.new_node(NodeKind::NodeSub, node, rhs);
+                node = self.new_sub(node, rhs);
             } else {
                 return node;
             }
         }
     }
 
-    // mul = unary ("*" unary | "/" unary)*
+    // mul = unary ("*" unary | "/" unary | "%" unary)*
     fn mul(&mut self) -> Tree {
         let mut node = self.unary();
 
@@ -317,62 +3162,701 @@ impl<'a> Parser<'a> {
             } else if self.lexer.consume("/") {
                 let rhs = self.unary();
                 node = self.new_node(NodeKind::NodeDiv, node, rhs);
+            } else if self.lexer.consume("%") {
+                let rhs = self.unary();
+                node = self.new_node(NodeKind::NodeMod, node, rhs);
             } else {
                 return node;
             }
         }
     }
 
-    // unary = ("+" | "-" | "*" | "&")? primary
+    // unary = "(" type-name ")" unary
+    //       | ("+" | "-" | "*" | "&" | "~") unary
+    //       | ("++" | "--") unary
+    //       | "sizeof" ("(" type-name ")" | unary)
+    //       | postfix
     fn unary(&mut self) -> Tree {
+        if self.lexer.consume("sizeof") {
+            return self.sizeof();
+        }
+        if self.is_cast() {
+            let loc = self.lexer.loc();
+            self.lexer.expect("(");
+            let ty = self.typename();
+            self.lexer.expect(")");
+            if self.lexer.peek("{") {
+                let node = self.compound_literal(ty, loc);
+                return self.postfix_ops(node);
+            }
+            let expr = self.unary();
+            let node = self.new_explicit_cast(expr, ty, loc);
+            return self.with_vla_setup(node);
+        }
         if self.lexer.consume("+") {
-            return self.primary();
+            return self.unary();
         }
         if self.lexer.consume("-") {
-            let zero = self.new_node_num(String::from("0"));
-            let rhs = self.primary();
-            return self.new_node(NodeKind::NodeSub, zero, rhs);
+            let zero = self.new_node_num(0);
+            let rhs = self.unary();
+            return self.new_sub(zero, rhs);
         }
         if self.lexer.consume("*") {
             let lhs = self.unary();
             return self.new_node(NodeKind::NodeDeref, lhs, None);
         }
         if self.lexer.consume("&") {
+            let loc = self.lexer.loc();
             let lhs = self.unary();
+            if lhs
+                .as_ref()
+                .unwrap()
+                .member
+                .as_ref()
+                .is_some_and(|m| m.is_bitfield)
+            {
+                self.lexer
+                    .report_at(loc, "cannot take the address of a bit-field");
+                process::exit(1);
+            }
+            if !Parser::is_lvalue(lhs.as_ref().unwrap()) {
+                self.lexer
+                    .report_at(loc, "lvalue required as unary '&' operand");
+                process::exit(1);
+            }
             return self.new_node(NodeKind::NodeAddr, lhs, None);
         }
-        return self.primary();
+        if self.lexer.consume("~") {
+            let lhs = self.unary();
+            return self.new_node(NodeKind::NodeBitNot, lhs, None);
+        }
+        // `++x` is `x += 1` and `--x` is `x -= 1`
+        let loc = self.lexer.loc();
+        if self.lexer.consume("++") {
+            let lhs = self.unary();
+            return self.new_incdec(lhs, NodeKind::NodeAdd, loc);
+        }
+        if self.lexer.consume("--") {
+            let lhs = self.unary();
+            return self.new_incdec(lhs, NodeKind::NodeSub, loc);
+        }
+        self.postfix()
+    }
+
+    // The operand of sizeof is not evaluated, only its type is used. The
+    // result is a size_t, which is an unsigned long.
+    fn sizeof(&mut self) -> Tree {
+        let loc = self.lexer.loc();
+        let ty = if self.is_cast() {
+            self.lexer.expect("(");
+            let ty = self.typename();
+            self.lexer.expect(")");
+            ty
+        } else {
+            let expr = self.unary();
+            Parser::ty_of(&expr)
+        };
+        let ty = self.complete_type(ty);
+        if !ty.is_complete() {
+            self.lexer
+                .report_at(loc, "invalid application of sizeof to an incomplete type");
+            process::exit(1);
+        }
+        let size = self.size_of(&ty);
+        self.with_vla_setup(size)
+    }
+
+    // A "(" followed by a type name starts a cast rather than a parenthesized
+    // expression.
+    fn is_cast(&mut self) -> bool {
+        if !self.lexer.peek("(") {
+            return false;
+        }
+        let saved = self.lexer.clone();
+        self.lexer.expect("(");
+        let is_cast = self.is_typename();
+        self.lexer = saved;
+        is_cast
+    }
+
+    // Scalars convert to each other, except floating-point values to and
+    // from pointers, and anything converts to void.
+    fn new_explicit_cast(&self, expr: Tree, ty: Type, loc: usize) -> Tree {
+        let mut from = Parser::ty_of(&expr);
+        if from.is_function() {
+            from = Type::pointer_to(from);
+        }
+        let is_scalar = |ty: &Type| ty.is_arith() || ty.is_pointer();
+        let is_valid = ty.kind == TypeKind::TyVoid
+            || (is_scalar(&from)
+                && is_scalar(&ty)
+                && !ty.is_array()
+                && !(from.is_flonum() && ty.is_pointer())
+                && !(from.is_pointer() && ty.is_flonum()));
+        if !is_valid {
+            self.lexer.report_at(loc, "invalid cast");
+            process::exit(1);
+        }
+        self.new_cast(expr, ty)
+    }
+
+    // postfix = (primary | compound-literal) postfix-op*
+    // postfix-op = "[" expr "]" | "(" func-args | "++" | "--" | "." ident | "->" ident
+    //
+    // A compound literal starts like a cast, so unary parses it and continues
+    // with its postfix operators.
+    fn postfix(&mut self) -> Tree {
+        let node = self.primary();
+        self.postfix_ops(node)
+    }
+
+    fn postfix_ops(&mut self, mut node: Tree) -> Tree {
+        loop {
+            let loc = self.lexer.loc();
+            if self.lexer.consume("(") {
+                node = self.indirect_call(node, loc);
+                continue;
+            }
+            if self.lexer.consume("[") {
+                // `a[i]` is `*(a + i)`
+                let index = self.expr();
+                self.lexer.expect("]");
+                let addr = self.new_add(node, index);
+                node = self.new_node(NodeKind::NodeDeref, addr, None);
+                continue;
+            }
+            if self.lexer.consume(".") {
+                node = self.struct_ref(node);
+                continue;
+            }
+            if self.lexer.consume("->") {
+                // `p->x` is `(*p).x`
+                let lhs = self.new_node(NodeKind::NodeDeref, node, None);
+                node = self.struct_ref(lhs);
+                continue;
+            }
+            // `x++` is `(x += 1) - 1` and `x--` is `(x -= 1) + 1`, so that
+            // the operand is evaluated only once
+            if self.lexer.consume("++") {
+                let ty = Parser::ty_of(&node);
+                let updated = self.new_incdec(node, NodeKind::NodeAdd, loc);
+                let one = self.new_node_num(1);
+                let prev = self.new_sub(updated, one);
+                node = self.new_cast(prev, ty);
+            } else if self.lexer.consume("--") {
+                let ty = Parser::ty_of(&node);
+                let updated = self.new_incdec(node, NodeKind::NodeSub, loc);
+                let one = self.new_node_num(1);
+                let prev = self.new_add(updated, one);
+                node = self.new_cast(prev, ty);
+            } else {
+                return node;
+            }
+        }
+    }
+
+    // compound-literal = "(" type-name ")" "{" initializer-list "}"
+    //
+    // An unnamed object initialized like a variable. Outside a function it is
+    // an anonymous global; inside one it lives in the enclosing block, and
+    // its initializer runs where the literal appears.
+    fn compound_literal(&mut self, ty: Type, loc: usize) -> Tree {
+        if ty.is_vla() {
+            self.lexer
+                .report_at(loc, "compound literal has variable size");
+            process::exit(1);
+        }
+        let (init, ty) = self.initializer(&ty);
+        if !ty.is_complete() {
+            self.lexer
+                .report_at(loc, "compound literal has an incomplete type");
+            process::exit(1);
+        }
+
+        if self.in_file_scope() {
+            let mut data = vec![0; ty.size];
+            let mut relocations = Vec::new();
+            self.write_global_data(&init, &mut data, 0, &mut relocations);
+            let name = format!(".L.compound.{}", self.globals.len());
+            self.globals.push(GlobalVar {
+                name: name.clone(),
+                ty: ty.clone(),
+                is_static: true,
+                is_definition: true,
+                init_data: Some(data),
+                relocations,
+            });
+            let var = LVar {
+                name,
+                offset: 0,
+                ty,
+                implicit: false,
+                is_global: true,
+            };
+            return self.new_node_var(var);
+        }
+
+        let var = self.new_lvar(String::new(), ty);
+        let mut raw_node = self.new_raw_node(NodeKind::NodeStmtExpr, None, None);
+        self.init_local(var.clone(), &init, &mut raw_node.body);
+        raw_node.ty = Some(Type::void());
+        let lhs = Some(Box::new(raw_node));
+        let rhs = self.new_node_var(var);
+        self.new_node(NodeKind::NodeCompoundLiteral, lhs, rhs)
+    }
+
+    fn struct_ref(&mut self, lhs: Tree) -> Tree {
+        let loc = self.lexer.loc();
+        let name = match self.lexer.is_ident_token() {
+            Some(name) => name,
+            None => {
+                self.lexer.report_at(loc, "expected a member name");
+                process::exit(1);
+            }
+        };
+        let ty = self.complete_type(Parser::ty_of(&lhs));
+        if !ty.is_struct() {
+            self.lexer
+                .report_at(loc, "member reference base type is not a struct");
+            process::exit(1);
+        }
+        let member = match ty.find_member(&name) {
+            Some(member) => member.clone(),
+            None => {
+                self.lexer
+                    .report_at(loc, &format!("no member named '{}'", name));
+                process::exit(1);
+            }
+        };
+
+        self.new_member(lhs, member)
+    }
+
+    fn new_member(&self, lhs: Tree, member: Member) -> Tree {
+        let mut ty = member.ty.clone();
+        // a bit-field whose values all fit in an int is read as one, even if
+        // it is unsigned. A _Bool keeps its type, which stores are converted
+        // to.
+        let fits_int = member.bit_width < 32 || member.bit_width == 32 && !ty.is_unsigned;
+        if member.is_bitfield && ty.is_integer() && ty.kind != TypeKind::TyBool && fits_int {
+            ty = Type {
+                is_const: ty.is_const,
+                ..Type::int(false)
+            };
+        }
+        // a member of a const struct is const
+        ty.is_const |= Parser::ty_of(&lhs).is_const;
+        let mut raw_node = self.new_raw_node(NodeKind::NodeMember, lhs, None);
+        raw_node.ty = Some(ty);
+        raw_node.member = Some(member);
+        Some(Box::new(raw_node))
+    }
+
+    // A pointer to a struct may have been made while the struct was still
+    // incomplete, such as a member pointing to its own struct. The struct is
+    // looked up again by its tag, to find its definition.
+    fn complete_type(&self, ty: Type) -> Type {
+        if ty.is_complete() {
+            return ty;
+        }
+        for scope in self.scopes.iter().rev() {
+            for (name, tag_ty) in scope.tags.iter() {
+                if Some(name) == ty.tag.as_ref() && tag_ty.kind == ty.kind && tag_ty.is_complete() {
+                    return Type {
+                        is_const: ty.is_const,
+                        ..tag_ty.clone()
+                    };
+                }
+            }
+        }
+        ty
+    }
+
+    fn new_incdec(&mut self, lhs: Tree, kind: NodeKind, loc: usize) -> Tree {
+        self.check_assignable(&lhs, loc);
+        let one = self.new_node_num(1);
+        self.new_assign_op(lhs, kind, one)
+    }
+
+    // Adjacent string literals are concatenated into one.
+    fn string_literal(&mut self) -> Option<Vec<u8>> {
+        let mut bytes = self.lexer.is_string_token()?;
+        while let Some(more) = self.lexer.is_string_token() {
+            bytes.extend(more);
+        }
+        Some(bytes)
     }
 
-    // primary = num | ident | "(" expr ")"
+    // primary = num | str | ident | funcall | "(" expr ")" | "(" "{" stmt-expr
+    //         | "_Generic" generic-selection
     fn primary(&mut self) -> Tree {
+        if self.lexer.consume("_Generic") {
+            return self.generic_selection();
+        }
         if self.lexer.consume("(") {
+            if self.lexer.consume("{") {
+                return self.stmt_expr();
+            }
             let node = self.expr();
             self.lexer.expect(")");
             return node;
         }
+        let loc = self.lexer.loc();
         if let Some(val) = self.lexer.is_ident_token() {
-            let node = Node {
-                kind: NodeKind::NodeLVar,
-                lhs: None,
-                rhs: None,
-                val: None,
-                offset: self.find_var(val),
-                body: Vec::new(),
-                cond: None,
-                then: None,
-                els: None,
-                init: None,
-                inc: None,
+            let is_var = matches!(self.find_symbol(&val), Some(Symbol::Var(_)));
+            if !is_var && self.lexer.consume("(") {
+                return match val.as_str() {
+                    "va_start" => self.va_start(loc),
+                    "va_arg" => self.va_arg(),
+                    "va_copy" => self.va_copy(),
+                    "va_end" => self.va_end(),
+                    "__builtin_alloca" => self.alloca(),
+                    _ => self.funcall(val, loc),
+                };
+            }
+            match self.find_symbol(&val) {
+                Some(Symbol::EnumConst(val)) => return self.new_node_num(*val),
+                Some(Symbol::Typedef(_)) => {
+                    self.lexer
+                        .report_at(loc, &format!("unexpected type name '{}'", val));
+                    process::exit(1);
+                }
+                Some(Symbol::Var(_)) => {}
+                None => {
+                    // a function designator, whose value is the address of
+                    // the function
+                    if let Some(function) = self.functions.iter().find(|f| f.name == val) {
+                        let var = LVar {
+                            name: val,
+                            offset: 0,
+                            ty: function.ty.clone(),
+                            implicit: false,
+                            is_global: true,
+                        };
+                        return self.new_node_var(var);
+                    }
+                }
+            }
+            let var = self.find_var(val);
+            return self.new_node_var(var);
+        }
+        if let Some((fval, is_float)) = self.lexer.is_float_token() {
+            return self.new_node_float(fval, is_float);
+        }
+        if let Some(bytes) = self.string_literal() {
+            let var = self.new_string_literal(bytes);
+            return self.new_node_var(var);
+        }
+
+        let literal = self.lexer.expect_number();
+        self.new_node_literal(literal, loc)
+    }
+
+    // generic-selection = "(" assign ("," generic-assoc)+ ")"
+    // generic-assoc = (type-name | "default") ":" assign
+    //
+    // The association whose type is that of the controlling expression,
+    // which is not evaluated, after the conversions it would undergo as an
+    // operand: qualifiers are dropped, and arrays and functions decay to
+    // pointers.
+    fn generic_selection(&mut self) -> Tree {
+        let loc = self.lexer.loc();
+        self.lexer.expect("(");
+        let control = self.assign();
+        let mut ty = Parser::ty_of(&control).unqualified();
+        if ty.is_array() {
+            ty = Type::pointer_to(*ty.base.unwrap());
+        } else if ty.is_function() {
+            ty = Type::pointer_to(ty);
+        }
+
+        let mut types: Vec<Option<Type>> = Vec::new();
+        let (mut selected, mut default) = (None, None);
+        while !self.lexer.consume(")") {
+            self.lexer.expect(",");
+            let assoc_loc = self.lexer.loc();
+            let assoc_ty = if self.lexer.consume("default") {
+                None
+            } else {
+                Some(self.typename())
             };
-            return Some(Box::new(node));
+            if types.contains(&assoc_ty) {
+                self.lexer
+                    .report_at(assoc_loc, "duplicate association in '_Generic'");
+                process::exit(1);
+            }
+            self.lexer.expect(":");
+            let expr = self.assign();
+            match assoc_ty {
+                Some(ref assoc_ty) if *assoc_ty == ty => selected = expr,
+                Some(_) => {}
+                None => default = expr,
+            }
+            types.push(assoc_ty);
+        }
+        match selected.or(default) {
+            Some(node) => Some(node),
+            None => {
+                self.lexer.report_at(
+                    loc,
+                    "'_Generic' selector matches no association and there is no default",
+                );
+                process::exit(1);
+            }
         }
+    }
 
-        if let Some(val) = self.lexer.expect_number() {
-            self.new_node_num(val)
-        } else {
-            eprintln!("parser: expected number");
+    // stmt-expr = block ")"
+    //
+    // A GNU extension: a block whose value is that of its last statement, or
+    // void if that is not an expression statement.
+    fn stmt_expr(&mut self) -> Tree {
+        self.stmt_expr_count += 1;
+        self.stmt_exprs.push(self.stmt_expr_count);
+        let block = self.block();
+        self.stmt_exprs.pop();
+        self.lexer.expect(")");
+        let block = block.unwrap();
+        let body = block.body;
+        // only expressions have a type
+        let ty = match body.last() {
+            Some(Some(last)) => last.ty.clone(),
+            _ => None,
+        };
+        let ty = ty.unwrap_or_else(Type::void);
+        let mut raw_node = self.new_raw_node(NodeKind::NodeStmtExpr, None, None);
+        raw_node.body = body;
+        // the first variable-length array of the block, freed at its end
+        raw_node.var = block.var;
+        raw_node.ty = Some(ty.unqualified());
+        Some(Box::new(raw_node))
+    }
+
+    // alloca = "__builtin_alloca" "(" assign ")"
+    //
+    // Stack memory that lives until the function returns, or until a scope
+    // with variable-length arrays declared before it is left.
+    fn alloca(&mut self) -> Tree {
+        let size = self.assign();
+        self.lexer.expect(")");
+        let size = self.new_cast(size, Type::long(true));
+        let mut raw_node = self.new_raw_node(NodeKind::NodeAlloca, size, None);
+        raw_node.ty = Some(Type::pointer_to(Type::void()));
+        Some(Box::new(raw_node))
+    }
+
+    // va-start = "va_start" "(" assign "," ident ")"
+    //
+    // The va_list is set up by the code generator, which knows how the named
+    // parameters are passed.
+    fn va_start(&mut self, loc: usize) -> Tree {
+        if self.va_area.is_none() {
+            self.lexer
+                .report_at(loc, "va_start used in a function with fixed arguments");
+            process::exit(1);
+        }
+        let ap = self.assign();
+        self.lexer.expect(",");
+        self.lexer.is_ident_token();
+        self.lexer.expect(")");
+        let mut raw_node = self.new_raw_node(NodeKind::NodeVaStart, ap, None);
+        raw_node.ty = Some(Type::void());
+        Some(Box::new(raw_node))
+    }
+
+    // va-arg = "va_arg" "(" assign "," type-name ")"
+    fn va_arg(&mut self) -> Tree {
+        let ap = self.assign();
+        self.lexer.expect(",");
+        let loc = self.lexer.loc();
+        let ty = self.typename();
+        self.lexer.expect(")");
+        if !ty.is_arith() && !ty.is_pointer() {
+            self.lexer
+                .report_at(loc, "va_arg of this type is not supported");
+            process::exit(1);
+        }
+        let mut raw_node = self.new_raw_node(NodeKind::NodeVaArg, ap, None);
+        raw_node.ty = Some(ty);
+        Some(Box::new(raw_node))
+    }
+
+    // va-copy = "va_copy" "(" assign "," assign ")"
+    //
+    // `va_copy(dst, src)` is `*dst = *src`, which copies the one element.
+    fn va_copy(&mut self) -> Tree {
+        let dst = self.assign();
+        self.lexer.expect(",");
+        let src = self.assign();
+        self.lexer.expect(")");
+        let dst = self.new_node(NodeKind::NodeDeref, dst, None);
+        let src = self.new_node(NodeKind::NodeDeref, src, None);
+        let copy = self.new_node(NodeKind::NodeAssign, dst, src);
+        self.new_cast(copy, Type::void())
+    }
+
+    // va-end = "va_end" "(" assign ")", which has nothing to clean up
+    fn va_end(&mut self) -> Tree {
+        let ap = self.assign();
+        self.lexer.expect(")");
+        self.new_cast(ap, Type::void())
+    }
+
+    // funcall = ident "(" (assign ("," assign)*)? ")"
+    //
+    // Arguments are converted to the parameter types. A function that has not
+    // been declared returns int, and its arguments only get the default
+    // argument promotions.
+    fn funcall(&mut self, name: String, loc: usize) -> Tree {
+        // an undeclared function returns int and takes any arguments
+        let ty = match self.functions.iter().find(|function| function.name == name) {
+            Some(function) => function.ty.clone(),
+            None => Type {
+                is_variadic: true,
+                ..Type::func(Type::int(false), Vec::new())
+            },
+        };
+
+        let args = self.func_args(&ty, &name, loc);
+        let mut raw_node = self.new_raw_node(NodeKind::NodeFuncall, None, None);
+        raw_node.val = Some(name);
+        self.set_return(&mut raw_node, *ty.return_ty.unwrap());
+        raw_node.args = args;
+        Some(Box::new(raw_node))
+    }
+
+    // Give a call the type it returns. A struct is returned to a buffer in
+    // the caller's frame, which is its value afterwards.
+    fn set_return(&mut self, node: &mut Node, ty: Type) {
+        if ty.is_struct() {
+            node.var = Some(self.new_lvar(String::new(), ty.clone()));
+        }
+        node.ty = Some(ty);
+    }
+
+    // A call through a pointer to a function, or any other expression of
+    // function type. The callee is kept in lhs.
+    fn indirect_call(&mut self, callee: Tree, loc: usize) -> Tree {
+        let callee_ty = Parser::ty_of(&callee);
+        let ty = match callee_ty.base {
+            Some(base) if callee_ty.kind == TypeKind::TyPtr && base.is_function() => *base,
+            _ if callee_ty.is_function() => callee_ty,
+            _ => {
+                self.lexer
+                    .report_at(loc, "called object is not a function or function pointer");
+                process::exit(1);
+            }
+        };
+
+        let args = self.func_args(&ty, "function pointer", loc);
+        let mut raw_node = self.new_raw_node(NodeKind::NodeFuncall, callee, None);
+        self.set_return(&mut raw_node, *ty.return_ty.unwrap());
+        raw_node.args = args;
+        Some(Box::new(raw_node))
+    }
+
+    // func-args = (assign ("," assign)*)? ")"
+    //
+    // Arguments are converted to the parameter types of a prototype, and
+    // the rest get the default argument promotions.
+    fn func_args(&mut self, ty: &Type, name: &str, loc: usize) -> Vec<Tree> {
+        let mut args = Vec::new();
+        while !self.lexer.consume(")") {
+            if !args.is_empty() {
+                self.lexer.expect(",");
+            }
+            let arg_loc = self.lexer.loc();
+            if !ty.is_variadic && args.len() == ty.params.len() {
+                self.lexer
+                    .report_at(loc, &format!("too many arguments to '{}'", name));
+                process::exit(1);
+            }
+            let arg = self.assign();
+            let arg_ty = Parser::ty_of(&arg);
+            if arg_ty.kind == TypeKind::TyVoid {
+                self.lexer
+                    .report_at(arg_loc, "void value not ignored as it ought to be");
+                process::exit(1);
+            }
+            if let Some(param_ty) = ty.params.get(args.len()) {
+                if Parser::discards_const(&arg_ty, param_ty) {
+                    self.lexer.report_at(
+                        arg_loc,
+                        &format!(
+                            "warning: passing argument {} of '{}' discards 'const' qualifier from pointer target type",
+                            args.len() + 1,
+                            name
+                        ),
+                    );
+                }
+            }
+            let param_ty = match ty.params.get(args.len()) {
+                Some(param_ty) => param_ty.clone(),
+                None if arg_ty.kind == TypeKind::TyFloat => Type::double(),
+                None if arg_ty.is_array() => Type::pointer_to(*arg_ty.base.unwrap()),
+                None if arg_ty.is_function() => Type::pointer_to(arg_ty),
+                None => arg_ty.promote(),
+            };
+            args.push(self.new_cast(arg, param_ty));
+        }
+        if args.len() < ty.params.len() {
+            self.lexer
+                .report_at(loc, &format!("too few arguments to '{}'", name));
             process::exit(1);
         }
+        args
+    }
+
+    // Whether converting a pointer of type `from` to `to` loses the const
+    // qualifier of what it points to.
+    fn discards_const(from: &Type, to: &Type) -> bool {
+        match (&from.base, &to.base) {
+            (Some(from_base), Some(to_base)) if from.is_pointer() && to.is_pointer() => {
+                from_base.is_const && !to_base.is_const
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The types of the globals and of the functions declared in `input`.
+    fn declared_types(input: &str) -> (Vec<Type>, Vec<Type>) {
+        let parser = Parser::parse(Tokenizer::tokenize(input));
+        let globals = parser.globals.into_iter().map(|global| global.ty);
+        let functions = parser.functions.into_iter().map(|function| function.ty);
+        (globals.collect(), functions.collect())
+    }
+
+    #[test]
+    fn test_declarator() {
+        let int = || Type::int(false);
+        let ptr = Type::pointer_to;
+        let arr = |ty, len| Type::array_of(ty, Some(len));
+        let konst = |ty: Type| Type {
+            is_const: true,
+            ..ty
+        };
+
+        let (globals, functions) = declared_types(
+            "int *a[3]; int (*b)[3]; int *(*c)(int *); int (*d[2])(void); int (*f(int))[3];",
+        );
+        assert_eq!(globals[0], arr(ptr(int()), 3));
+        assert_eq!(globals[1], ptr(arr(int(), 3)));
+        assert_eq!(globals[2], ptr(Type::func(ptr(int()), vec![ptr(int())])));
+        assert_eq!(globals[3], arr(ptr(Type::func(int(), Vec::new())), 2));
+        assert_eq!(functions[0], Type::func(ptr(arr(int(), 3)), vec![int()]));
+
+        let (globals, _) = declared_types(
+            "const int *const volatile a; long unsigned int b; int long signed c; \
+             char *restrict d[2]; int f(void);",
+        );
+        assert_eq!(globals[0], konst(ptr(konst(int()))));
+        assert_eq!(globals[1], Type::long(true));
+        assert_eq!(globals[2], Type::long(false));
+        assert_eq!(globals[3], arr(ptr(Type::char(false)), 2));
     }
 }
@@ -0,0 +1,656 @@
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    TyBool,
+    TyChar,
+    TyShort,
+    TyInt,
+    TyLong,
+    TyLongLong,
+    TyEnum,
+    TyFloat,
+    TyDouble,
+    TyVoid,
+    TyPtr,
+    TyArray,
+    TyFunc,
+    TyStruct,
+    TyUnion,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub size: usize,
+    pub align: usize,
+    pub is_unsigned: bool,
+    pub is_const: bool,
+    pub base: Option<Box<Type>>, // pointee of a pointer, or element of an array
+
+    // for TyArray: the number of elements, or None if it is not known yet
+    pub array_len: Option<usize>,
+    // for a variable-length array: the frame offset of the local that holds
+    // its size in bytes, which is only known at run time
+    pub vla_size: Option<usize>,
+
+    // for TyFunc
+    pub return_ty: Option<Box<Type>>,
+    pub params: Vec<Type>,
+    pub is_variadic: bool,
+
+    // for TyStruct and TyUnion: the tag, and the members or None if the type
+    // is declared but not defined yet
+    pub tag: Option<String>,
+    pub members: Option<Vec<Member>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    pub offset: usize,
+
+    // for a bit-field: where its bits start in the storage unit of type `ty`
+    // at `offset`, and how many there are
+    pub is_bitfield: bool,
+    pub bit_offset: usize,
+    pub bit_width: usize,
+}
+
+impl Member {
+    // The bits of a bit-field, before they are shifted into place.
+    pub fn bit_mask(&self) -> u64 {
+        if self.bit_width == 64 {
+            u64::MAX
+        } else {
+            (1 << self.bit_width) - 1
+        }
+    }
+}
+
+// A member as declared: its name, which only an unnamed bit-field lacks,
+// its type, and its width if it is a bit-field.
+pub type MemberDecl = (Option<String>, Type, Option<usize>);
+
+impl Type {
+    fn new(kind: TypeKind, size: usize, is_unsigned: bool) -> Self {
+        Type {
+            kind,
+            size,
+            align: size,
+            is_unsigned,
+            is_const: false,
+            base: None,
+            array_len: None,
+            vla_size: None,
+            return_ty: None,
+            params: Vec::new(),
+            is_variadic: false,
+            tag: None,
+            members: None,
+        }
+    }
+
+    pub fn bool() -> Self {
+        Type::new(TypeKind::TyBool, 1, true)
+    }
+
+    pub fn char(is_unsigned: bool) -> Self {
+        Type::new(TypeKind::TyChar, 1, is_unsigned)
+    }
+
+    pub fn short(is_unsigned: bool) -> Self {
+        Type::new(TypeKind::TyShort, 2, is_unsigned)
+    }
+
+    pub fn int(is_unsigned: bool) -> Self {
+        Type::new(TypeKind::TyInt, 4, is_unsigned)
+    }
+
+    pub fn long(is_unsigned: bool) -> Self {
+        Type::new(TypeKind::TyLong, 8, is_unsigned)
+    }
+
+    pub fn long_long(is_unsigned: bool) -> Self {
+        Type::new(TypeKind::TyLongLong, 8, is_unsigned)
+    }
+
+    // An enum is compatible with int, whose size and alignment it has.
+    pub fn enumeration() -> Self {
+        Type::new(TypeKind::TyEnum, 4, false)
+    }
+
+    pub fn float() -> Self {
+        Type::new(TypeKind::TyFloat, 4, false)
+    }
+
+    pub fn double() -> Self {
+        Type::new(TypeKind::TyDouble, 8, false)
+    }
+
+    pub fn void() -> Self {
+        Type::new(TypeKind::TyVoid, 1, false)
+    }
+
+    pub fn pointer_to(base: Type) -> Self {
+        Type {
+            base: Some(Box::new(base)),
+            ..Type::new(TypeKind::TyPtr, 8, true)
+        }
+    }
+
+    pub fn vla_of(base: Type, size_offset: usize) -> Self {
+        Type {
+            align: base.align,
+            vla_size: Some(size_offset),
+            base: Some(Box::new(base)),
+            ..Type::new(TypeKind::TyArray, 0, false)
+        }
+    }
+
+    pub fn array_of(base: Type, len: Option<usize>) -> Self {
+        let size = base.size * len.unwrap_or(0);
+        Type {
+            align: base.align,
+            array_len: len,
+            base: Some(Box::new(base)),
+            ..Type::new(TypeKind::TyArray, size, false)
+        }
+    }
+
+    pub fn func(return_ty: Type, params: Vec<Type>) -> Self {
+        Type {
+            return_ty: Some(Box::new(return_ty)),
+            params,
+            ..Type::new(TypeKind::TyFunc, 1, false)
+        }
+    }
+
+    // A struct or union with only a tag. Its members are filled in by
+    // `layout` once the definition is seen.
+    pub fn incomplete_struct(tag: Option<String>) -> Self {
+        Type {
+            tag,
+            ..Type::new(TypeKind::TyStruct, 0, false)
+        }
+    }
+
+    pub fn incomplete_union(tag: Option<String>) -> Self {
+        Type {
+            tag,
+            ..Type::new(TypeKind::TyUnion, 0, false)
+        }
+    }
+
+    // Lay out the members the way the SysV ABI does: each member of a struct
+    // at the next offset aligned for it, and every member of a union at
+    // offset 0. The whole is padded to a multiple of its strictest member
+    // alignment.
+    pub fn layout(&mut self, members: Vec<(String, Type)>) {
+        let members = members
+            .into_iter()
+            .map(|(name, ty)| (Some(name), ty, None))
+            .collect();
+        self.layout_bitfields(members);
+    }
+
+    // Lay out members that may include bit-fields. A bit-field is packed
+    // right after the previous member, unless that would make it cross a
+    // boundary aligned for its type, in which case it starts at the
+    // boundary. A bit-field of width 0 only moves to the next boundary. The
+    // types of unnamed bit-fields do not count towards the alignment.
+    pub fn layout_bitfields(&mut self, members: Vec<MemberDecl>) {
+        let is_struct = self.kind == TypeKind::TyStruct;
+        // the end of the last member, and the size so far, in bits
+        let mut bits = 0;
+        let mut size_bits = 0;
+        let mut align = 1;
+        let mut laid_out = Vec::new();
+        for (name, ty, width) in members {
+            let unit = ty.size * 8;
+            let start = match width {
+                Some(0) => {
+                    if is_struct {
+                        bits = align_to(bits, unit);
+                    }
+                    continue;
+                }
+                Some(width) if bits / unit != (bits + width - 1) / unit => align_to(bits, unit),
+                Some(_) => bits,
+                None => align_to(bits, ty.align * 8),
+            };
+            let start = if is_struct { start } else { 0 };
+            let end = start + width.unwrap_or(ty.size * 8);
+            if is_struct {
+                bits = end;
+            }
+            size_bits = size_bits.max(end);
+
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+            align = align.max(ty.align);
+            let member = match width {
+                Some(width) => Member {
+                    name,
+                    offset: start / unit * ty.size,
+                    is_bitfield: true,
+                    bit_offset: start % unit,
+                    bit_width: width,
+                    ty,
+                },
+                None => Member {
+                    name,
+                    ty,
+                    offset: start / 8,
+                    is_bitfield: false,
+                    bit_offset: 0,
+                    bit_width: 0,
+                },
+            };
+            laid_out.push(member);
+        }
+        self.size = align_to(size_bits.div_ceil(8), align);
+        self.align = align;
+        self.members = Some(laid_out);
+    }
+
+    // The same type without its qualifiers, which is what the value of an
+    // object of this type has.
+    pub fn unqualified(&self) -> Type {
+        Type {
+            is_const: false,
+            ..self.clone()
+        }
+    }
+
+    // Whether an object of this type may not be modified at all: it is
+    // const, or an array of const elements.
+    pub fn is_readonly(&self) -> bool {
+        match self.base {
+            Some(ref base) if self.is_array() => self.is_const || base.is_readonly(),
+            _ => self.is_const,
+        }
+    }
+
+    // Whether a struct or union has a member that may not be modified, at
+    // any depth, which makes assigning the whole object invalid too.
+    pub fn has_readonly_member(&self) -> bool {
+        let members = match self.members {
+            Some(ref members) => members,
+            None => return false,
+        };
+        members.iter().any(|member| {
+            let mut ty = &member.ty;
+            while ty.is_array() && !ty.is_const {
+                ty = ty.base.as_ref().unwrap();
+            }
+            ty.is_const || ty.has_readonly_member()
+        })
+    }
+
+    pub fn find_member(&self, name: &str) -> Option<&Member> {
+        self.members
+            .as_ref()?
+            .iter()
+            .find(|member| member.name == name)
+    }
+
+    // Whether this is a struct or a union, which are handled alike.
+    pub fn is_struct(&self) -> bool {
+        self.kind == TypeKind::TyStruct || self.kind == TypeKind::TyUnion
+    }
+
+    pub fn is_complete(&self) -> bool {
+        match self.kind {
+            TypeKind::TyArray => self.array_len.is_some() || self.is_vla(),
+            _ => !self.is_struct() || self.members.is_some(),
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.kind == TypeKind::TyArray
+    }
+
+    pub fn is_vla(&self) -> bool {
+        self.vla_size.is_some()
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.kind == TypeKind::TyFunc
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self.kind,
+            TypeKind::TyBool
+                | TypeKind::TyChar
+                | TypeKind::TyShort
+                | TypeKind::TyInt
+                | TypeKind::TyLong
+                | TypeKind::TyLongLong
+                | TypeKind::TyEnum
+        )
+    }
+
+    pub fn is_flonum(&self) -> bool {
+        self.kind == TypeKind::TyFloat || self.kind == TypeKind::TyDouble
+    }
+
+    pub fn is_arith(&self) -> bool {
+        self.is_integer() || self.is_flonum()
+    }
+
+    // Arrays count as pointers, since they decay to a pointer to their first
+    // element wherever they are used as a value.
+    pub fn is_pointer(&self) -> bool {
+        self.kind == TypeKind::TyPtr || self.kind == TypeKind::TyArray
+    }
+
+    // Integer conversion rank, see C11 6.3.1.1.
+    fn rank(&self) -> usize {
+        match self.kind {
+            TypeKind::TyChar => 1,
+            TypeKind::TyShort => 2,
+            TypeKind::TyInt | TypeKind::TyEnum => 3,
+            TypeKind::TyLong => 4,
+            TypeKind::TyLongLong => 5,
+            _ => 0,
+        }
+    }
+
+    // Integer promotion: types narrower than int, and enums, are converted to
+    // int, which can represent all of their values.
+    pub fn promote(&self) -> Type {
+        if self.kind == TypeKind::TyEnum
+            || self.is_integer() && self.rank() < Type::int(false).rank()
+        {
+            Type::int(false)
+        } else {
+            self.unqualified()
+        }
+    }
+
+    // The common type of the usual arithmetic conversions, see C11 6.3.1.8.
+    pub fn common(lhs: &Type, rhs: &Type) -> Type {
+        if lhs.kind == TypeKind::TyDouble || rhs.kind == TypeKind::TyDouble {
+            return Type::double();
+        }
+        if lhs.kind == TypeKind::TyFloat || rhs.kind == TypeKind::TyFloat {
+            return Type::float();
+        }
+
+        let lhs = lhs.promote();
+        let rhs = rhs.promote();
+        if lhs == rhs {
+            return lhs;
+        }
+        if lhs.is_unsigned == rhs.is_unsigned {
+            return if lhs.rank() >= rhs.rank() { lhs } else { rhs };
+        }
+
+        let (unsigned, signed) = if lhs.is_unsigned {
+            (lhs, rhs)
+        } else {
+            (rhs, lhs)
+        };
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.size > unsigned.size {
+            signed
+        } else {
+            Type {
+                is_unsigned: true,
+                ..signed
+            }
+        }
+    }
+
+    // Convert a constant to this type, the way a value in a register is
+    // truncated and then sign- or zero-extended to 64 bits. A _Bool only
+    // holds 0 or 1, so any nonzero value becomes 1.
+    pub fn truncate(&self, val: i64) -> i64 {
+        if self.kind == TypeKind::TyBool {
+            return (val != 0) as i64;
+        }
+        match (self.size, self.is_unsigned) {
+            (1, false) => val as i8 as i64,
+            (1, true) => val as u8 as i64,
+            (2, false) => val as i16 as i64,
+            (2, true) => val as u16 as i64,
+            (4, false) => val as i32 as i64,
+            (4, true) => val as u32 as i64,
+            _ => val,
+        }
+    }
+
+    // How a struct or union is passed and returned under the SysV ABI, one
+    // entry per eightbyte: true if it goes in an xmm register because it
+    // holds only floating-point data, and false if it goes in a
+    // general-purpose one. Anything larger than 16 bytes goes through
+    // memory, which is None.
+    pub fn eightbytes(&self) -> Option<Vec<bool>> {
+        if self.size > 16 {
+            return None;
+        }
+        let classes = (0..self.size.div_ceil(8))
+            .map(|i| self.only_flonum(i * 8, i * 8 + 8, 0))
+            .collect();
+        Some(classes)
+    }
+
+    // Whether the scalars of this type, placed at `offset`, that overlap the
+    // bytes from `lo` to `hi` are all floating-point.
+    fn only_flonum(&self, lo: usize, hi: usize, offset: usize) -> bool {
+        match self.kind {
+            TypeKind::TyStruct | TypeKind::TyUnion => self
+                .members
+                .as_ref()
+                .unwrap()
+                .iter()
+                .all(|member| member.ty.only_flonum(lo, hi, offset + member.offset)),
+            TypeKind::TyArray => {
+                let base = self.base.as_ref().unwrap();
+                (0..self.array_len.unwrap_or(0))
+                    .all(|i| base.only_flonum(lo, hi, offset + i * base.size))
+            }
+            _ => offset + self.size <= lo || hi <= offset || self.is_flonum(),
+        }
+    }
+}
+
+// Round up `n` to the nearest multiple of `align`
+pub fn align_to(n: usize, align: usize) -> usize {
+    n.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_common() {
+        let common = Type::common(&Type::char(false), &Type::short(true));
+        assert_eq!(common, Type::int(false));
+        let common = Type::common(&Type::int(false), &Type::int(true));
+        assert_eq!(common, Type::int(true));
+        let common = Type::common(&Type::long(false), &Type::int(true));
+        assert_eq!(common, Type::long(false));
+        let common = Type::common(&Type::long_long(false), &Type::long(true));
+        assert_eq!(common, Type::long_long(true));
+        let common = Type::common(&Type::long(true), &Type::float());
+        assert_eq!(common, Type::float());
+        let common = Type::common(&Type::float(), &Type::double());
+        assert_eq!(common, Type::double());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(Type::char(false).truncate(256), 0);
+        assert_eq!(Type::char(false).truncate(255), -1);
+        assert_eq!(Type::int(true).truncate(-1), 0xffffffff);
+        assert_eq!(Type::bool().truncate(256), 1);
+        assert_eq!(Type::bool().truncate(-1), 1);
+        assert_eq!(Type::bool().truncate(0), 0);
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut ty = Type::incomplete_struct(None);
+        ty.layout(vec![
+            (String::from("a"), Type::char(false)),
+            (String::from("b"), Type::double()),
+            (String::from("c"), Type::short(false)),
+        ]);
+        let offsets: Vec<usize> = ty
+            .members
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|m| m.offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 8, 16]);
+        assert_eq!((ty.size, ty.align), (24, 8));
+
+        let mut ty = Type::incomplete_union(None);
+        ty.layout(vec![
+            (String::from("a"), Type::char(false)),
+            (String::from("b"), Type::int(false)),
+            (String::from("c"), Type::short(false)),
+        ]);
+        let offsets: Vec<usize> = ty
+            .members
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|m| m.offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 0, 0]);
+        assert_eq!((ty.size, ty.align), (4, 4));
+    }
+
+    #[test]
+    fn test_has_readonly_member() {
+        let const_int = Type {
+            is_const: true,
+            ..Type::int(false)
+        };
+        let mut inner = Type::incomplete_struct(None);
+        inner.layout(vec![
+            (String::from("a"), Type::int(false)),
+            (String::from("b"), Type::array_of(const_int, Some(2))),
+        ]);
+        assert!(inner.has_readonly_member());
+
+        let mut outer = Type::incomplete_union(None);
+        outer.layout(vec![
+            (String::from("x"), Type::long(false)),
+            (String::from("y"), Type::array_of(inner, Some(3))),
+        ]);
+        assert!(outer.has_readonly_member());
+
+        let mut plain = Type::incomplete_struct(None);
+        plain.layout(vec![(
+            String::from("p"),
+            Type::pointer_to(Type {
+                is_const: true,
+                ..Type::char(false)
+            }),
+        )]);
+        assert!(!plain.has_readonly_member());
+    }
+
+    #[test]
+    fn test_layout_bitfields() {
+        let bits = |ty: &Type| -> Vec<(usize, usize, usize)> {
+            ty.members
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|m| (m.offset, m.bit_offset, m.bit_width))
+                .collect()
+        };
+        let name = |name: &str| Some(String::from(name));
+
+        // a field that would cross a unit boundary starts a new unit
+        let mut ty = Type::incomplete_struct(None);
+        ty.layout_bitfields(vec![
+            (name("a"), Type::int(true), Some(3)),
+            (name("b"), Type::int(true), Some(30)),
+        ]);
+        assert_eq!(bits(&ty), vec![(0, 0, 3), (4, 0, 30)]);
+        assert_eq!((ty.size, ty.align), (8, 4));
+
+        let mut ty = Type::incomplete_struct(None);
+        ty.layout_bitfields(vec![
+            (name("a"), Type::int(false), Some(1)),
+            (name("b"), Type::long(false), Some(40)),
+            (name("c"), Type::char(false), None),
+        ]);
+        assert_eq!(bits(&ty), vec![(0, 0, 1), (0, 1, 40), (6, 0, 0)]);
+        assert_eq!((ty.size, ty.align), (8, 8));
+
+        // unnamed fields take up room but do not align the struct
+        let mut ty = Type::incomplete_struct(None);
+        ty.layout_bitfields(vec![
+            (name("c"), Type::char(false), None),
+            (None, Type::int(false), Some(0)),
+            (name("d"), Type::char(false), None),
+        ]);
+        assert_eq!(bits(&ty), vec![(0, 0, 0), (4, 0, 0)]);
+        assert_eq!((ty.size, ty.align), (5, 1));
+
+        // a flexible array member adds no size, only alignment
+        let mut ty = Type::incomplete_struct(None);
+        ty.layout_bitfields(vec![
+            (name("c"), Type::char(false), None),
+            (name("d"), Type::array_of(Type::int(false), None), None),
+        ]);
+        assert_eq!(bits(&ty), vec![(0, 0, 0), (4, 0, 0)]);
+        assert_eq!((ty.size, ty.align), (4, 4));
+    }
+
+    #[test]
+    fn test_eightbytes() {
+        let eightbytes = |members: Vec<Type>| {
+            let mut ty = Type::incomplete_struct(None);
+            ty.layout(
+                members
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, ty)| (format!("m{}", i), ty))
+                    .collect(),
+            );
+            ty.eightbytes()
+        };
+
+        assert_eq!(
+            eightbytes(vec![Type::float(), Type::float(), Type::int(false)]),
+            Some(vec![true, false])
+        );
+        assert_eq!(
+            eightbytes(vec![Type::char(false), Type::double()]),
+            Some(vec![false, true])
+        );
+        assert_eq!(
+            eightbytes(vec![Type::array_of(Type::float(), Some(3))]),
+            Some(vec![true, true])
+        );
+        assert_eq!(
+            eightbytes(vec![
+                Type::long(false),
+                Type::long(false),
+                Type::char(false)
+            ]),
+            None
+        );
+
+        // the members of a union overlap, so an int in it spoils a double
+        let mut ty = Type::incomplete_union(None);
+        ty.layout(vec![
+            (String::from("d"), Type::double()),
+            (String::from("i"), Type::int(false)),
+        ]);
+        assert_eq!(ty.eightbytes(), Some(vec![false]));
+    }
+}
@@ -1,10 +1,95 @@
 #!/bin/bash
+# functions for the test programs to call, compiled by the system compiler
+cat <<EOF | cc -xc -c -o tmp2.o -
+#include <stdarg.h>
+#include <stddef.h>
+double add_double(double x, double y) { return x + y; }
+float add_float(float x, float y) { return x + y; }
+int check_mixed(int a, double b, long c, float d, char e) {
+    return a == 1 && b == 2.5 && c == -3 && d == 4.5f && e == 'x';
+}
+double sum_doubles(double a, double b, double c, double d, double e,
+                   double f, double g, double h, double i, double j) {
+    return a + b + c + d + e + f + g + h + i + j;
+}
+long sum_longs(long a, long b, long c, long d, long e, long f, long g, long h) {
+    return a + b + c + d + e + f + g + h;
+}
+struct mixed { char a; int b; char c; double d; short e; };
+struct nested { char tag; struct mixed m; float f; struct { short s; char c; } anon; };
+long mixed_layout(int i) {
+    long layout[] = {sizeof(struct mixed), offsetof(struct mixed, a), offsetof(struct mixed, b),
+                     offsetof(struct mixed, c), offsetof(struct mixed, d), offsetof(struct mixed, e)};
+    return layout[i];
+}
+long nested_layout(int i) {
+    long layout[] = {sizeof(struct nested), offsetof(struct nested, m), offsetof(struct nested, f),
+                     offsetof(struct nested, anon), sizeof(((struct nested *)0)->anon)};
+    return layout[i];
+}
+void fill_mixed(struct mixed *m) { m->a = 1; m->b = 2; m->c = 3; m->d = 4.5; m->e = 5; }
+long sum_mixed(struct mixed *m) { return m->a + m->b + m->c + m->d + m->e; }
+struct point { int x; int y; };
+int sum_points(struct point *p, int n) {
+    int sum = 0;
+    for (int i = 0; i < n; i++) sum += p[i].x * 10 + p[i].y;
+    return sum;
+}
+double sum_va(int n, ...) {
+    va_list ap;
+    va_start(ap, n);
+    double sum = 0;
+    for (int i = 0; i < n; i++) sum += va_arg(ap, double);
+    va_end(ap);
+    return sum;
+}
+int ext_count = 5;
+int ext_arr[] = {1, 2, 3};
+int get_ext_count(void) { return ext_count; }
+// clash with the static definitions of the test programs unless those are
+// kept local
+int helper_id(void) { return 9; }
+int shadowed = 9;
+int apply_twice(int (*f)(int), int x) { return f(f(x)); }
+static int triple(int x) { return x * 3; }
+int (*get_triple(void))(int) { return triple; }
+long sp_here(void) { return (long)__builtin_frame_address(0); }
+struct bits { unsigned a : 3; int b : 5; unsigned c : 30; char d; long e : 40; unsigned char f : 1; };
+long bits_size(void) { return sizeof(struct bits); }
+void fill_bits(struct bits *p) { p->a = 5; p->b = -7; p->c = 123456789; p->d = 'x'; p->e = -1234567890; p->f = 1; }
+int check_bits(struct bits *p) { return p->a == 6 && p->b == -3 && p->c == 1000 && p->d == 7 && p->e == 99 && p->f == 0; }
+struct packet { unsigned short len; unsigned char kind : 4; unsigned char flags : 4; char data[]; };
+int packet_sum(struct packet *p) { int s = p->kind * 16 + p->flags; for (int i = 0; i < p->len; i++) s += p->data[i]; return s; }
+struct s3 { char a, b, c; };
+struct ff { float x, y, z; };
+struct dl { double d; long l; };
+struct fi { float f; int i; };
+struct ll { long a, b; };
+struct big { long a, b, c; };
+union ud { double d; long l; };
+int sum_s3(struct s3 s) { return s.a + s.b + s.c; }
+struct s3 make_s3(int a, int b, int c) { struct s3 s = {a, b, c}; return s; }
+double sum_ff(struct ff s) { return s.x + s.y + s.z; }
+struct ff make_ff(float x, float y, float z) { struct ff s = {x, y, z}; return s; }
+long sum_dl(struct dl s) { return (long)s.d + s.l; }
+struct dl make_dl(double d, long l) { struct dl s = {d, l}; return s; }
+long sum_fi(struct fi s) { return (long)s.f * s.i; }
+struct fi make_fi(float f, int i) { struct fi s = {f, i}; return s; }
+long sum_big(struct big s) { return s.a + s.b * 10 + s.c * 100; }
+struct big make_big(long a, long b, long c) { struct big s = {a, b, c}; return s; }
+long spill_ll(long a, long b, long c, long d, long e, struct ll s, long f) { return a + b + c + d + e + s.a * 10 + s.b * 100 + f * 1000; }
+long spill_dl(double a, double b, double c, double d, double e, double f, double g, struct dl s, double h) { return (long)(a + b + c + d + e + f + g + h) + (long)s.d * 100 + s.l * 1000; }
+long ud_bits(union ud u) { return u.l; }
+long apply_big(struct big (*f)(struct big, struct ff)) { struct big b = {1, 2, 3}; struct ff x = {0.5f, 1.5f, 2.0f}; struct big r = f(b, x); return r.a + r.b * 10 + r.c * 100; }
+double apply_dl(struct dl (*f)(struct s3, struct dl)) { struct s3 a = {1, 2, 3}; struct dl b = {2.5, 4}; struct dl r = f(a, b); return r.d + r.l; }
+EOF
+
 assert() {
     expected="$1"
     input="$2"
 
     cargo run -- "$input" > tmp.s
-    cc -o tmp tmp.s
+    cc -o tmp tmp.s tmp2.o
     ./tmp
     actual="$?"
 
@@ -18,6 +103,34 @@ assert() {
     rm tmp tmp.s
 }
 
+# Check that compiling `input` fails with a message containing `expected`.
+assert_error() {
+    expected="$1"
+    input="$2"
+
+    output=$(cargo run -q -- "$input" 2>&1 > /dev/null)
+    if [ "$?" != 0 ] && [[ "$output" == *"$expected"* ]]; then
+        echo "$input => $expected"
+    else
+        echo "$input => error '$expected' expected, but got: $output"
+        exit 1
+    fi
+}
+
+# Check that compiling `input` succeeds with a message containing `expected`.
+assert_warning() {
+    expected="$1"
+    input="$2"
+
+    output=$(cargo run -q -- "$input" 2>&1 > /dev/null)
+    if [ "$?" = 0 ] && [[ "$output" == *"$expected"* ]]; then
+        echo "$input => $expected"
+    else
+        echo "$input => warning '$expected' expected, but got: $output"
+        exit 1
+    fi
+}
+
 assert 0 "0;"
 assert 42 "42;"
 assert 21 "5+20-4;"
@@ -69,4 +182,499 @@ assert 3 "x = 3; y = &x; z = &y; return **z;"
 assert 2 "x = 1; y = 2; return *(&x - 8);"
 assert 1 "x = 1; y = 2; return *(&y + 8);"
 assert 2 "x = 1; y = &x; *y = 2; return x;"
+assert 2 "17 % 5;"
+assert 4 "2 + 3 % 2 * 2;"
+assert 2 "6 & 3;"
+assert 7 "6 | 3;"
+assert 5 "6 ^ 3;"
+assert 3 "1 | 2 ^ 3 & 4;"
+assert 1 "1 | 2 == 3;"
+assert 2 "~-3;"
+assert 0 "~0 + 1;"
+assert 40 "5 << 3;"
+assert 5 "40 >> 3;"
+assert 4 "1 << 1 + 1;"
+assert 1 "1 << 3 > 7;"
+assert 255 "-1 >> 1 & 255;"
+assert 2 "x = 17; x %= 5; x;"
+assert 2 "x = 6; x &= 3; x;"
+assert 7 "x = 6; x |= 3; x;"
+assert 5 "x = 6; x ^= 3; x;"
+assert 40 "x = 5; x <<= 3; x;"
+assert 5 "x = 40; x >>= 3; x;"
+assert 12 "x = 3; y = x <<= 2; y;"
+assert 6 "x = 3; p = &x; *p <<= 1; x;"
+assert 3 "x = 1; y = &x; *y |= 2; x;"
+assert 7 "x = 3; x += 4; x;"
+assert 1 "x = 3; x -= 2; x;"
+assert 12 "x = 3; x *= 4; x;"
+assert 3 "x = 13; x /= 4; x;"
+assert 9 "x = 3; y = x += 6; y;"
+assert 9 "x = 2; y = 3; x += y *= 2 + 1; x + y - 11;"
+assert 4 "x = 3; ++x;"
+assert 4 "x = 3; ++x; x;"
+assert 2 "x = 3; --x;"
+assert 3 "x = 3; x++;"
+assert 4 "x = 3; x++; x;"
+assert 3 "x = 3; x--;"
+assert 2 "x = 3; x--; x;"
+assert 55 "j = 0; for (i = 0; i <= 10; i++) j += i; j;"
+assert 4 "x = 1; y = 7; p = &y; *p++ = 3; y + (p - &y);"
+assert 9 "x = 5; p = &x; q = &p; *(*q)++ = 8; x + (p - &x);"
+assert 4 "x = 1; p = &x; *p-- += 2; x + (&x - p);"
+assert 2 "1 ? 2 : 3;"
+assert 3 "0 ? 2 : 3;"
+assert 4 "x = 0; x ? 2 : 1 + 3;"
+assert 5 "x = 2; x == 1 ? 4 : x == 2 ? 5 : 6;"
+assert 6 "x = 1; y = 0; x ? y ? 7 : 6 : 8;"
+assert 9 "x = 1; y = x ? 9 : 10; y;"
+assert 3 "x = 0; x ? 1 : (x = 3); x;"
+assert 3 "1, 2, 3;"
+assert 5 "x = (1, 5); x;"
+assert 4 "x = 1, y = 3; x + y;"
+assert 5 "if (1) if (0) 3; else return 5; 7;"
+assert 30 "s = 0; for (i = 0, j = 10; i < j; i++, j--) s += j - i; s;"
+assert 3 "i = 0; for (;;) { if (i == 3) break; i++; } i;"
+assert 4 "i = 0; while (i >= 0) { i++; if (i > 3) break; } i;"
+assert 25 "s = 0; for (i = 0; i < 10; i++) { if (i % 2 == 0) continue; s += i; } s;"
+assert 30 "s = 0; i = 0; while (i < 10) { i++; if (i % 2) continue; s += i; } s;"
+assert 6 "s = 0; for (i = 0; i < 3; i++) for (j = 0; j < 10; j++) { if (j == 2) break; s += 1; } s;"
+assert 10 "i = 0; do { i++; } while (i < 10); i;"
+assert 1 "i = 0; do i++; while (0); i;"
+assert 5 "i = 0; do { i++; if (i == 5) break; } while (1); i;"
+assert 3 "i = 0; goto a; a: i++; b: i++; c: i++; i;"
+assert 2 "i = 0; goto c; a: i++; b: i++; c: i++; i + 1;"
+assert 1 "i = 0; goto b; a: i++; b: i++; i;"
+assert 10 "i = 0; loop: i++; if (i < 10) goto loop; i;"
+assert 5 "x = 5; goto end; x = 6; end: return x;"
+assert 5 "x = 2; y = 0; switch (x) { case 1: y = 3; break; case 2: y = 5; break; case 3: y = 7; break; } y;"
+assert 7 "x = 9; y = 0; switch (x) { case 1: y = 3; break; default: y = 7; } y;"
+assert 0 "x = 9; y = 0; switch (x) { case 1: y = 3; break; case 2: y = 5; } y;"
+assert 12 "x = 1; y = 0; switch (x) { case 1: y += 3; case 2: y += 4; case 3: y += 5; } y;"
+assert 9 "x = 2; y = 0; switch (x) { default: y += 1; case 1: y += 3; break; case 2: y += 9; } y;"
+assert 4 "x = 5; y = 0; switch (x) { default: y += 1; case 1: y += 3; break; case 2: y += 9; } y;"
+assert 8 "x = 3; y = 0; switch (x) { case 1 + 1: y = 4; break; case 6 / 2: y = 8; break; } y;"
+assert 3 "x = -1; y = 0; switch (x) { case 1: y = 1; break; case -1: y = 3; break; } y;"
+assert 40 "x = 4; y = 0; switch (x) { case 0: y = 10; break; case 1: y = 11; break; case 2: y = 20; break; case 3: y = 30; break; case 4: y = 40; break; case 6: y = 60; break; } y;"
+assert 99 "x = 5; y = 0; switch (x) { case 0: y = 10; break; case 1: y = 11; break; case 2: y = 20; break; case 3: y = 30; break; case 4: y = 40; break; case 6: y = 60; break; default: y = 99; } y;"
+assert 0 "x = 7; y = 0; switch (x) { case 0: y = 10; break; case 1: y = 11; break; case 2: y = 20; break; case 3: y = 30; break; case 4: y = 40; break; case 6: y = 60; break; } y;"
+assert 0 "x = -3; y = 0; switch (x) { case 0: y = 10; break; case 1: y = 11; break; case 2: y = 20; break; case 3: y = 30; break; case 4: y = 40; break; } y;"
+assert 70 "x = 700; y = 0; switch (x) { case 1: y = 10; break; case 50: y = 20; break; case 100: y = 30; break; case 300: y = 40; break; case 500: y = 50; break; case 600: y = 60; break; case 700: y = 70; break; case 9000: y = 80; break; } y;"
+assert 10 "x = 1; y = 0; switch (x) { case 1: y = 10; break; case 50: y = 20; break; case 100: y = 30; break; case 300: y = 40; break; case 500: y = 50; break; case 600: y = 60; break; case 700: y = 70; break; case 9000: y = 80; break; } y;"
+assert 80 "x = 9000; y = 0; switch (x) { case 1: y = 10; break; case 50: y = 20; break; case 100: y = 30; break; case 300: y = 40; break; case 500: y = 50; break; case 600: y = 60; break; case 700: y = 70; break; case 9000: y = 80; break; } y;"
+assert 1 "x = 650; y = 1; switch (x) { case 1: y = 10; break; case 50: y = 20; break; case 100: y = 30; break; case 300: y = 40; break; case 500: y = 50; break; case 600: y = 60; break; case 700: y = 70; break; case 9000: y = 80; break; } y;"
+assert 6 "s = 0; for (i = 0; i < 4; i++) { switch (i) { case 1: continue; case 2: s += 2; break; default: s += 2; } } s;"
+assert 5 "x = 1; y = 0; switch (x) { case 1: switch (y) { case 0: y = 5; break; case 1: y = 6; } break; case 2: y = 9; } y;"
+assert 3 "int x; x = 3; x;"
+assert 5 "int x = 2, y = 3; x + y;"
+assert 2 "int x = 2; { int x = 3; } x;"
+assert 3 "int x = 2; { int x = 3; return x; }"
+assert 5 "int x = 2; { x = 5; } x;"
+assert 7 "int x = 2; { int x = 3; { int x = 7; return x; } }"
+assert 4 "{ int x = 1; } { int x = 4; return x; }"
+assert 6 "int x = 1; { int y = 2; { int x = 3; y = y * x; } x = y; } x;"
+assert 10 "int s = 0; for (int i = 0; i < 5; i++) s += i; s;"
+assert 3 "int i = 3; for (int i = 0; i < 5; i++) 1; i;"
+assert 5 "int x = 5; { int y = 1; z = 2; } { int w = 9; } z + x - 2;"
+assert 12 "{ int a = 3; b = 4; { int c = 5; } { int d = 6; b = b * a; } } b;"
+assert 1 "int x = 1; { int x = x + 1; } x;"
+assert 4 "i = 0; while (1) { i++; if (i > 3) break; } i;"
+assert 0 "i = 0; for (; 0;) i = 1; i;"
+assert 3 "i = 0; for (; 1;) { i++; if (i == 3) break; } i;"
+assert 8 "i = 0; s = 0; do { i++; if (i % 2) continue; s += i; } while (i < 6); s - 4;"
+assert 7 "x = 7; y = 0; for (i = 0; i < 100000; i++) if (0) y = 1; x;"
+assert 9 "x = 9; for (i = 0; i < 100000; i++) { i; i; } x;"
+assert 1 "char c = 127; c++; c < 0;"
+assert 128 "char c = 127; c = c + 1; c;"
+assert 127 "char c = 127; int d = c++; d;"
+assert 0 "unsigned char c = 255; c++; c;"
+assert 1 "unsigned char c = 200; c + c == 400;"
+assert 255 "char c = -1; unsigned char u = c; u;"
+assert 44 "int x = 300; char c = x; c;"
+assert 1 "short s = 32767; s + 1 == 32768;"
+assert 0 "unsigned short s = 65535; s++; s;"
+assert 1 "short s = -1; unsigned short u = s; u == 65535;"
+assert 1 "int x = 2147483647; x + 1 < 0;"
+assert 1 "long x = 2147483647; x + 1 > 0;"
+assert 1 "unsigned x = 0; x - 1 > 0;"
+assert 0 "int x = -1; unsigned y = 1; x < y;"
+assert 1 "long x = -1; unsigned y = 1; x < y;"
+assert 0 "unsigned long x = 1; long y = -1; y < x;"
+assert 0 "long long x = -1; unsigned long y = 1; x <= y;"
+assert 1 "int x = -7; x / 2 == -3;"
+assert 1 "unsigned x = -8; x / 2 == 2147483644;"
+assert 1 "int x = -8; x >> 1 == -4;"
+assert 1 "unsigned x = -8; x >> 1 == 2147483644;"
+assert 1 "unsigned long x = -1; x >> 63;"
+assert 1 "unsigned x = 7; -x > 0;"
+assert 255 "int x = -1; x % 3;"
+assert 0 "unsigned x = -1; x % 3;"
+assert 1 "long long x = 1; x << 40 >> 40;"
+assert 1 "unsigned int x = 1; x << 32 == 0;"
+assert 10 "int i = 0; for (unsigned char c = 250; c != 4; c++) i++; i;"
+assert 1 "signed char c = -2; c >> 1 == -1;"
+assert 1 "short int s = 1; long int l = 2; long long int ll = 3; unsigned long long u = 4; s + l + ll + u == 10;"
+assert 1 "int a = 1; int b = 2; int *p = &b; *(p + 1);"
+assert 5 "long a = 5; long *p = &a; long **pp = &p; **pp;"
+assert 7 "int x = 3; int *p = &x; *p += 4; x;"
+assert 1 "int x = 1; int *p = &x; p++; p - &x;"
+assert 3 "char a = 1; char b = 2; char *p = &b; *p + *(p + 1);"
+assert 1 "unsigned x = -1; int y = 0; switch (x) { case -1: y = 1; } y;"
+assert 2 "char c = -1; int y = 0; switch (c) { case 255: y = 1; break; case -1: y = 2; } y;"
+assert 3 "unsigned long x = -2; int y = 0; switch (x) { case 1: y = 1; break; case 2: y = 2; break; case -2: y = 3; break; case 3: y = 4; break; case 4: y = 5; break; case -1: y = 6; } y;"
+assert_error "invalid type argument of unary '*'" "int x = 1; *x;"
+assert_error "lvalue required as unary '&' operand" "&1;"
+assert 1 "double x = 0x1.8p1; x == 3.0;"
+assert 1 "1e2 == 100;"
+assert 1 ".5 + .5 == 1;"
+assert 1 "float f = 1.5f; f * 2 == 3;"
+assert 1 "float f = 0.1; double d = f; d != 0.1;"
+assert 3 "double d = 3.9; int i = d; i;"
+assert 253 "int i = -3.9; i;"
+assert 1 "int i = 7; i / 2.0 == 3.5;"
+assert 3 "int i = 7; double d = i / 2; int r = d; r;"
+assert 10 "unsigned long u = 1e19; u / 1000000000 / 1000000000;"
+assert 1 "unsigned long u = -1; double d = u; d == 0x1p64;"
+assert 1 "double d = 1.5; d++; d == 2.5;"
+assert 4 "double x = 0; int n = 0; while (x < 1) { x += 0.25; n++; } n;"
+assert 2 "int r = 0; if (0.5 - 0.5) r = 1; else r = 2; r;"
+assert 0 "double z = 0; z = z * -1; int r = 0; if (z) r = 1; r;"
+assert 0 "double z = 0; double n = z / z; n == n;"
+assert 1 "double z = 0; double n = z / z; n != n;"
+assert 0 "double z = 0; double n = z / z; (n < 1) + (n >= 1);"
+assert 7 "double half(double x) { return x / 2; } int main() { return half(7.0) * 2; }"
+assert 55 "double sum(double a, double b, double c, double d, double e, double f, double g, double h, double i, double j) { return a + b + c + d + e + f + g + h + i + j; } int main() { return sum(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }"
+assert 36 "int sum(int a, int b, int c, int d, int e, int f, int g, int h) { return a + b + c + d + e + f + g + h; } int main() { return sum(1, 2, 3, 4, 5, 6, 7, 8); }"
+assert 21 "int fib(int n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } int main() { return fib(8); }"
+assert 1 "double add_double(double x, double y); int main() { return add_double(0.5, 0.25) == 0.75; }"
+assert 1 "float add_float(float x, float y); int main() { return add_float(1, 2.5) == 3.5; }"
+assert 1 "int check_mixed(int a, double b, long c, float d, char e); int main() { return check_mixed(1, 2.5, -3, 4.5, 120); }"
+assert 55 "double sum_doubles(double a, double b, double c, double d, double e, double f, double g, double h, double i, double j); int main() { return sum_doubles(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }"
+assert 56 "double sum_doubles(double a, double b, double c, double d, double e, double f, double g, double h, double i, double j); int main() { return 1 + sum_doubles(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }"
+assert 36 "long x = 0; x + sum_longs(1, 2, 3, 4, 5, 6, 7, 8);"
+assert_error "invalid operands to binary +" "void f() {} int main() { return 1 + f(); }"
+assert_error "void value not ignored" "void f() {} int main() { int x = f(); return x; }"
+assert_error "void value not ignored" "void f() {} int g(int x) { return x; } int main() { return g(f()); }"
+assert_error "too many arguments to 'f'" "int f(int x) { return x; } int main() { return f(1, 2); }"
+assert 16 "0x10;"
+assert 8 "010;"
+assert 5 "0b101;"
+assert 255 "0XFF;"
+assert 0 "0;"
+assert 1 "0xffffffff + 1 == 0;"
+assert 1 "4294967295 + 1 == 4294967296;"
+assert 1 "-2147483648 < 0;"
+assert 0 "-1 < 0u;"
+assert 0 "-1L < 0UL;"
+assert 1 "-1 < 0L;"
+assert 1 "0x7fffffffffffffff > 0;"
+assert 1 "0x8000000000000000 > 0;"
+assert 1 "18446744073709551615u == -1;"
+assert 1 "1ll << 40 >> 40;"
+assert 1 "unsigned long u = 0xffffffffffffffff; u / 1000000000000000000 == 18;"
+assert 44 "(char)300;"
+assert 255 "(unsigned char)-1;"
+assert 1 "(signed char)200 < 0;"
+assert 1 "(short)65537;"
+assert 3 "(int)3.99;"
+assert 1 "(double)7 / 2 == 3.5;"
+assert 1 "(unsigned)-1 > 0;"
+assert 1 "(long)(int)4294967295 == -1;"
+assert 1 "-(char)255;"
+assert 3 "- -3;"
+assert 2 "(void)1; 2;"
+assert 0 "(float)0.1 == 0.1;"
+assert 1 "(float)0.5 == 0.5;"
+assert 3 "int x = 0x01020304; char *p = (char *)&x; *(p + 1);"
+assert 5 "int x = 5; long a = (long)&x; *(int *)a;"
+assert 8 "long x = 0; long *p = &x; (char *)(p + 1) - (char *)p;"
+assert 7 "int r = 0; switch (3) { case (char)259: r = 7; } r;"
+assert 42 "double half(double x) { return x / 2; } int main() { return (int)half(84.9); }"
+assert 4 "sizeof(int);"
+assert 8 "sizeof(char *);"
+assert 2 "short x; sizeof x;"
+assert 16 "sizeof(long) * 2;"
+assert 16 "sizeof(struct { char a; double b; });"
+assert 1 "struct s { char c; } s; sizeof s;"
+assert 7 "struct p { int x; int y; } a, b; a.x = 3; a.y = 4; b = a; a.x = 10; b.x + b.y;"
+assert 3 "struct node { int val; struct node *next; }; struct node a; struct node b; a.val = 1; a.next = &b; b.val = 2; a.next->val + a.val;"
+assert 2 "struct node { int val; struct node *next; } a, b; a.next = &b; b.val = 2; struct node *p = &a; p->next->val;"
+assert 31 "struct in { char c; long l; }; struct out { char t; struct in i; } o; o.i.l = 5; o.i.c = 2; o.i.l + o.i.c + sizeof(o);"
+assert 6 "struct s { int a; } x; int r = 0; { struct s { char b; char c; } y; r = sizeof(y); } r + sizeof(x);"
+assert 9 "struct p { int x; int y; } a; struct p *q = &a; q->y = 9; (*q).y;"
+assert 3 "struct node { struct node *next; int v; } a, b; a.v = 3; a.next = &b; *a.next = a; b.v;"
+assert 5 "struct node { struct node *next; int v; } a, b; b.v = 5; a.next = &b; struct node c = *a.next; c.v;"
+assert 13 "struct node; struct node *p; struct node { int v; long w; } m, n; n.v = 4; n.w = 9; p = &n; m = *p; m.v + m.w;"
+assert_error "invalid operands to binary +" "struct s { int a; }; int main() { struct s a; return 1 + a; }"
+assert 6 "struct mixed { char a; int b; char c; double d; short e; }; long mixed_layout(int i); int main() { struct mixed m; return (sizeof(struct mixed) == mixed_layout(0)) + ((char *)&m.a - (char *)&m == mixed_layout(1)) + ((char *)&m.b - (char *)&m == mixed_layout(2)) + ((char *)&m.c - (char *)&m == mixed_layout(3)) + ((char *)&m.d - (char *)&m == mixed_layout(4)) + ((char *)&m.e - (char *)&m == mixed_layout(5)); }"
+assert 5 "struct mixed { char a; int b; char c; double d; short e; }; struct nested { char tag; struct mixed m; float f; struct { short s; char c; } anon; }; long nested_layout(int i); int main() { struct nested n; return (sizeof(n) == nested_layout(0)) + ((char *)&n.m - (char *)&n == nested_layout(1)) + ((char *)&n.f - (char *)&n == nested_layout(2)) + ((char *)&n.anon - (char *)&n == nested_layout(3)) + (sizeof(n.anon) == nested_layout(4)); }"
+assert 15 "struct mixed { char a; int b; char c; double d; short e; }; void fill_mixed(struct mixed *m); int main() { struct mixed m; fill_mixed(&m); return m.a + m.b + m.c + m.d + m.e; }"
+assert 15 "struct mixed { char a; int b; char c; double d; short e; }; long sum_mixed(struct mixed *m); int main() { struct mixed m; m.a = 1; m.b = 2; m.c = 3; m.d = 4.5; m.e = 5; return sum_mixed(&m); }"
+assert 8 "union u { char c; int i; double d; }; sizeof(union u);"
+assert 6 "union { char c; short s; char b; } u; sizeof(u) + 4;"
+assert 1 "union u { int i; char c; } x; x.i = 257; x.c;"
+assert 12 "struct s { char c; union { char a; long l; } u; }; sizeof(struct s) - 4;"
+assert 3 "enum color { red, green, blue }; blue + green;"
+assert 17 "enum { a = 5, b, c = 10, d, }; b + d;"
+assert 4 "enum e { x = 1 << 2 } v; v = x; sizeof(v);"
+assert 3 "typedef int t; t x = 3; x;"
+assert 8 "typedef long t; t a = 4; t * p = &a; *p * 2;"
+assert 7 "typedef struct { int x; int y; } point; point p; p.x = 3; p.y = 4; p.x + p.y;"
+assert 2 "typedef char t; t x = 1; { int t = 2; x = t; } x;"
+assert 8 "typedef char *str, t; sizeof(str) + sizeof(t) - 1;"
+assert 9 "typedef int myint; myint twice(myint x) { return x * 2; } int main() { return twice(4) + 1; }"
+assert 6 "int a[] = {1, 2, 3}; a[0] + a[1] + a[2];"
+assert 3 "int a[] = {1, 2, 3}; sizeof(a) / sizeof(a[0]);"
+assert 0 "int a[5] = {1, 2}; a[2] + a[3] + a[4];"
+assert 3 "int a[2] = {1, 2, 3}; a[0] + a[1];"
+assert 11 "int a[2][3] = {{1, 2, 3}, {4, 5, 6}}; a[0][2] + a[1][0] + a[1][2] - a[0][0] - 1;"
+assert 21 "int a[2][3] = {1, 2, 3, 4, 5, 6}; a[0][0] + a[0][1] + a[0][2] + a[1][0] + a[1][1] + a[1][2];"
+assert 7 "int a[10] = {[3] = 7}; a[3] + a[0] + a[9];"
+assert 7 "int a[] = {1, 2, [5] = 3, 4}; sizeof(a) / 4;"
+assert 4 "int a[] = {1, 2, [5] = 3, 4}; a[6];"
+assert 3 "int a[3][2] = {[1][1] = 3}; a[1][1] + a[0][0];"
+assert 4 "struct p { int x; int y; } v = {1, 3}; v.x + v.y;"
+assert 5 "struct p { int x; int y; } v = {.y = 5}; v.x + v.y;"
+assert 10 "struct p { int x; int y; } v[2] = {1, 2, 3, 4}; v[0].x + v[0].y + v[1].x + v[1].y;"
+assert 10 "struct p { int x; int y; } v[2] = {{1, 2}, [1].y = 7}; v[0].x + v[0].y + v[1].y + v[1].x;"
+assert 7 "struct { int a; struct { int b; int c; } in; } s = {.in.c = 6, .a = 1}; s.a + s.in.c + s.in.b;"
+assert 5 "struct { int a; struct { int b; int c; } in; } s = {1, 2, 3}; s.a + s.in.c + 1;"
+assert 11 "struct p { int x; int y; } a = {3, 4}; struct p b = a; b.x + b.y + 4;"
+assert 7 "union u { int i; char c; } x = {.c = 7}; x.c;"
+assert 3 "int x = {3}; x;"
+assert 98 "char s[] = \"abc\"; s[1];"
+assert 4 "char s[] = \"abc\"; sizeof(s);"
+assert 0 "char s[10] = \"abc\"; s[3] + s[9];"
+assert 99 "char *p = \"abc\"; p[2];"
+assert 5 "char s[] = \"a\" \"bcd\"; sizeof(s);"
+assert 10 "char s[] = \"\\n\\x41\\101\"; s[0] + s[1] - s[2];"
+assert 6 "struct s { char n[4]; int v; } x = {\"ab\", 3}; x.n[0] - 97 + x.n[1] - 98 + x.v + 3;"
+assert 5 "int strlen(char *s); int main() { return strlen(\"hello\"); }"
+assert 10 "int sum(int *a, int n) { int s = 0; for (int i = 0; i < n; i++) s += a[i]; return s; } int main() { int a[] = {1, 2, 3, 4}; return sum(a, 4); }"
+assert 8 "int g = 3; int h[] = {1, 5}; int main() { return g + h[1]; }"
+assert 0 "int x; int arr[100]; int main() { return x + arr[99]; }"
+assert 14 "int x = 2 * 7; int main() { return x; }"
+assert 1 "long x = -1; int main() { return x == -1; }"
+assert 1 "double d = 1.5; float f = 2.5f; int main() { return d + f == 4; }"
+assert 200 "unsigned char c[] = {200}; int main() { return c[0]; }"
+assert 99 "char s[5] = \"abc\"; int main() { return s[2] + s[3] + s[4]; }"
+assert 6 "struct s { int a[3]; } g = {{1, 2, 3}}; int main() { return g.a[0] + g.a[1] + g.a[2]; }"
+assert 1 "union { char c[4]; int i; } u = {{1, 0, 0, 0}}; int main() { return u.i; }"
+assert 3 "int x = 3; int *p = &x; int main() { return *p; }"
+assert 2 "int a[3] = {1, 2, 3}; int *p = &a[1]; int main() { return *p; }"
+assert 20 "struct s { int n; int *p; }; int v[] = {10, 20}; struct s g = {2, v + 1}; int main() { return *g.p; }"
+assert 104 "char *strs[] = {\"ab\", \"hi\"}; int main() { return strs[1][0]; }"
+assert 46 "struct point { int x; int y; }; struct point pts[] = {{1, 2}, [2] = {.y = 4, .x = 3}}; int sum_points(struct point *p, int n); int main() { return sum_points(pts, sizeof(pts) / sizeof(pts[0])) + pts[1].x; }"
+assert 46 "struct point { int x; int y; }; int sum_points(struct point *p, int n); int main() { struct point pts[] = {{1, 2}, [2] = {.y = 4, .x = 3}}; return sum_points(pts, 3); }"
+assert 7 "double sum_va(int n, ...); int main() { return sum_va(3, 1.5, 2.0, 3.5); }"
+assert 55 "int sum(int n, ...) { va_list ap; va_start(ap, n); int s = 0; for (int i = 0; i < n; i++) s += va_arg(ap, int); va_end(ap); return s; } int main() { return sum(10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }"
+assert 33 "double sum(int n, ...) { va_list ap; va_start(ap, n); double s = 0; for (int i = 0; i < n; i++) s += va_arg(ap, double); va_end(ap); return s; } int main() { return sum(11, 1.0, 2.0, 3.0, 4.0, 5.0, 1.0, 2.0, 3.0, 4.0, 5.0, 3.0); }"
+assert 21 "long mix(int a, double b, ...) { va_list ap; va_start(ap, b); long x = va_arg(ap, long); double y = va_arg(ap, double); char *s = va_arg(ap, char *); va_end(ap); return a + b + x + y + s[1]; } int main() { return mix(1, 2.5, 3, 4.5, \"a\x0a\"); }"
+assert 6 "int second(int n, ...) { va_list ap; va_list aq; va_start(ap, n); va_arg(ap, int); va_copy(aq, ap); va_arg(ap, int); int x = va_arg(aq, int); va_end(ap); va_end(aq); return x; } int main() { return second(0, 5, 6, 7); }"
+assert 2 "int vsnprintf(char *buf, long n, char *fmt, va_list ap); int format(char *buf, char *fmt, ...) { va_list ap; va_start(ap, fmt); int n = vsnprintf(buf, 100, fmt, ap); va_end(ap); return n; } int main() { char buf[100]; int n = format(buf, \"%d %s %.1f %x\", 42, \"ab\", 2.5, 255); return (n == 12) + (strcmp(buf, \"42 ab 2.5 ff\") == 0); }"
+assert 5 "extern int ext_count; int main() { return ext_count; }"
+assert 8 "extern int ext_count; int get_ext_count(void); int main() { ext_count = 8; return get_ext_count(); }"
+assert 3 "extern int ext_arr[]; int main() { return ext_arr[2]; }"
+assert 5 "int main() { extern int ext_count; return ext_count; }"
+assert 3 "static int helper_id(void) { return 3; } int main() { return helper_id(); }"
+assert 2 "static int shadowed = 2; int main() { return shadowed; }"
+assert 4 "static int f(void); int main() { return f(); } static int f(void) { return 4; }"
+assert 7 "extern int g; int g = 7; extern int g; int main() { return g; }"
+assert 6 "static int g = 6; extern int g; int main() { extern int g; return g; }"
+assert 3 "int x; int x; int main() { x = 3; return x; }"
+assert 3 "int count() { static int n; n = n + 1; return n; } int main() { count(); count(); return count(); }"
+assert 12 "int count() { static int n = 10; n = n + 1; return n; } int main() { count(); return count(); }"
+assert 22 "int a() { static int n = 1; return n++; } int b() { static int n = 20; return n++; } int main() { a(); return a() + b(); }"
+assert 6 "int *next() { static int buf[3] = {1, 2, 3}; static int i; return &buf[i++]; } int main() { return *next() + *next() + *next(); }"
+assert 3 "static int n; n = 3; n;"
+assert_error "non-static declaration of 'x' follows static one" "static int x; int x; int main() { return x; }"
+assert 7 "int add(int a, int b) { return a + b; } int main() { int (*fp)(int, int) = add; return fp(3, 4); }"
+assert 7 "int add(int a, int b) { return a + b; } int main() { int (*fp)(int, int) = &add; return (*fp)(3, 4) + (**fp)(0, 0); }"
+assert 12 "int sq(int x) { return x * x; } int apply_twice(int (*f)(int), int x); int main() { return apply_twice(sq, 2) - 4; }"
+assert 18 "int (*get_triple(void))(int); int main() { return get_triple()(6); }"
+assert 6 "int apply(int f(int), int x) { return f(x); } int dbl(int x) { return x * 2; } int main() { return apply(dbl, 3); }"
+assert 11 "int add(int a, int b) { return a + b; } int sub(int a, int b) { return a - b; } int mul(int a, int b) { return a * b; } int (*ops[3])(int, int) = {add, sub, mul}; int main() { return ops[0](1, 2) + ops[1](10, 4) + ops[2](1, 2); }"
+assert 13 "int add(int a, int b) { return a + b; } int sub(int a, int b) { return a - b; } int mul(int a, int b) { return a * b; } int main() { int (*ops[])(int, int) = {add, sub, mul}; int r = 0; for (int i = 0; i < 3; i++) r += ops[i](i + 1, 2); return r + 4; }"
+assert 5 "typedef int (*binop)(int, int); struct calc { binop op; int arg; }; int add(int a, int b) { return a + b; } int main() { struct calc c = {add, 2}; struct calc *p = &c; return p->op(p->arg, 3); }"
+assert 9 "double half(double x) { return x / 2; } int main() { double (*f)(double) = half; return f(18.0); }"
+assert 1 "int add(int a, int b) { return a + b; } int main() { int (*fp)(int, int) = add; return fp == add; }"
+assert 8 "int main() { int (*fp)(int, int); return sizeof(fp) + sizeof(int (*)(int)) - 8; }"
+assert 2 "int printf(char *fmt, ...); int main() { int (*p)(char *, ...) = printf; return p(\"%d\\n\", 1); }"
+assert 1 "int strlen(char *s); int main() { int (*p)(char *) = &strlen; return p == strlen; }"
+assert 4 "int id(int x) { return x; } int main() { long p = (long)id; int (*f)(int) = (int (*)(int))p; return f(4); }"
+assert 10 "int sum(int n, ...) { va_list ap; va_start(ap, n); int s = 0; for (int i = 0; i < n; i++) s += va_arg(ap, int); va_end(ap); return s; } int main() { int (*f)(int, ...) = sum; return f(4, 1, 2, 3, 4); }"
+assert 30 "int main() { int (*a)[3]; int b[2][3] = {{1, 2, 3}, {4, 5, 6}}; a = b; return a[1][2] * 5; }"
+assert 24 "int main() { return sizeof(int *[3]); }"
+assert 8 "int main() { return sizeof(int (*)[3]); }"
+assert 24 "int main() { return sizeof(int (*[3])(void)); }"
+assert 12 "int main() { int (*p)[3]; return sizeof(*p); }"
+assert 3 "int main() { const int x = 3; return x; }"
+assert 5 "int main() { int v = 5; int *const p = &v; volatile int w = *p; return w; }"
+assert 8 "int main() { long unsigned int x = 8; return sizeof(x) == 8 ? x : 0; }"
+assert 2 "int main() { int long signed x = -1; unsigned short int y = 65535; return (x + 2 == 1) + (y == 65535); }"
+assert 7 "char *copy(char *restrict dst, const char *restrict src) { char *d = dst; while ((*d++ = *src++)) 0; return dst; } int main() { char buf[8]; return copy(buf, \"abcdefg\")[6] - 96; }"
+assert 6 "int g[3] = {4, 5, 6}; int (*rowp(void))[3] { return &g; } int main() { return (*rowp())[2]; }"
+assert 9 "int nine(void) { return 9; } int (*pick(int i))(void) { return nine; } int main() { int (*(*fp)(int))(void) = pick; return fp(0)(); }"
+assert 2 "int main() { int a[2][3] = {{1, 2, 3}, {4, 5, 6}}; int (*p)[3] = a; int *q[2] = {a[0], a[1]}; return ((p + 1) - p) + (q[1] - q[0]) / 3; }"
+assert 3 "const int tbl[3] = {1, 2, 3}; int main() { return tbl[2]; }"
+assert 5 "struct p { int x; int y; }; const struct p origin = {2, 3}; int main() { return origin.x + origin.y; }"
+assert 98 "int main() { const char *s = \"abc\"; s = s + 1; return *s; }"
+assert 4 "int main() { int a = 3; int *const p = &a; *p = 4; return a; }"
+assert 7 "int f() { static const int k = 7; return k; } int main() { return f(); }"
+assert 2 "int len(char *s) { int n = 0; while (s[n]) n++; return n; } int main() { const char *s = \"ab\"; return len(s); }"
+assert 9 "int f(const int x) { return x * 3; } int f(int x); int main() { return f(3); }"
+assert 6 "typedef const int cint; int main() { cint a = 2; const cint b = 4; return a + b; }"
+assert 5 "struct s { int v; }; int main() { const struct s a = {5}; struct s b; b = a; return b.v; }"
+assert 139 "const int g = 1; int main() { *(int *)&g = 2; return g; }"
+assert_error "cannot assign to a read-only location" "int main() { const int x = 1; x = 2; return x; }"
+assert_error "cannot assign to a read-only location" "struct s { const int x; } a = {1}, b = {2}; int main() { a = b; return a.x; }"
+assert_error "cannot assign to a read-only location" "struct t { struct { const int x; } in; int y; }; int main() { struct t a = {{1}, 2}, b = a; a = b; return 0; }"
+assert_warning "discards 'const' qualifier" "int len(char *s) { return 0; } int main() { const char *s = \"ab\"; return len(s); }"
+assert 1 "int main() { _Bool b = 256; return b; }"
+assert 1 "int main() { return sizeof(_Bool); }"
+assert 1 "int main() { bool b = 0.5; return b; }"
+assert 0 "int main() { bool b = 0.0; return b; }"
+assert 1 "int main() { int x; int *p = &x; _Bool b = p; return b; }"
+assert 0 "int main() { int *p = 0; _Bool b; b = p; return b; }"
+assert 1 "int main() { _Bool b = 1; b++; b++; return b; }"
+assert 1 "int main() { _Bool b = 0; b += 4; return b; }"
+assert 3 "int main() { bool a = true; bool b = false; return a + a + a + b; }"
+assert 3 "int true = 3; int main() { return true; }"
+assert 6 "typedef int bool; int main() { int false = 4; bool b = 2; return false + b; }"
+assert 1 "int main() { return (_Bool)512 + (_Bool)0; }"
+assert 1 "_Bool g = 2; int main() { return g; }"
+assert 1 "_Bool g = 0.25; int main() { return g; }"
+assert 2 "int main() { _Bool a[2] = {7, 8}; return a[0] + a[1]; }"
+assert 1 "_Bool is_odd(int x) { return x % 2; } int main() { return is_odd(7); }"
+assert 1 "int f(_Bool b) { return b; } int main() { return f(1024); }"
+assert 1 "int main() { long x = 4294967296; _Bool b = x; return b; }"
+assert 3 "int main() { return ({ 1; 2; 3; }); }"
+assert 8 "int main() { int x = ({ int y = 3; y + 5; }); return x; }"
+assert 10 "int main() { int s = 0; int t = ({ int i; for (i = 0; i < 5; i++) s = s + i; s; }); return t; }"
+assert 7 "int main() { return 1 + ({ int a = 2; a * 3; }); }"
+assert 6 "int add(int a, int b) { return a + b; } int main() { return add(1, ({ int t = add(2, 3); t; })); }"
+assert 4 "int main() { int a = 1; ({ a = 4; }); return a; }"
+assert 5 "int main() { return ({ int x = ({ 2; }); x + 3; }); }"
+assert 2 "int main() { double d = ({ 2.5; }); return d; }"
+assert 3 "int main() { int x = 0; ({ if (1) x = 3; }); return x; }"
+assert 3 "struct p { int x; int y; }; int main() { return ({ struct p v = {1, 2}; v; }).y + 1; }"
+assert 192 "int main() { long s = 0; for (int i = 0; i < 1000000; i++) { s += 1 + ({ if (i & 1) continue; 5; }); } return s % 256; }"
+assert 0 "int printf(char *fmt, ...); int main() { double t = 0; for (int i = 0; i < 5; i++) { t += 1 + ({ if (i < 2) goto next; 5; }); next: printf(\"%.1f\", 1.5); } return t != 18; }"
+assert 3 "struct p { int x; int y; }; int main() { struct p v = (struct p){1, 2}; return v.x + v.y; }"
+assert 2 "struct p { int x; int y; }; int main() { return (struct p){1, 2}.y; }"
+assert 6 "int main() { int *a = (int[]){1, 2, 3}; return a[0] + a[1] + a[2]; }"
+assert 3 "int main() { return sizeof((char[]){1, 2, 3}); }"
+assert 5 "int main() { int *p = &(int){5}; return *p; }"
+assert 2 "struct p { int x; }; int main() { struct p a = {1}, b = {2}; return (a, b).x; }"
+assert 9 "int main() { int *p = &(int){5}; *p = 9; return *p; }"
+assert 0 "struct p { int x; int y; }; int main() { return (struct p){.y = 2}.x; }"
+assert 7 "int *g = (int[]){4, 7}; int main() { return g[1]; }"
+assert 12 "struct p { int x; int y; }; int sum(struct p *p) { return p->x + p->y; } int main() { return sum(&(struct p){5, 7}); }"
+assert 15 "int main() { int s = 0; int i; for (i = 0; i < 5; i++) { int *p = (int[]){i, 1}; s = s + p[0] + p[1]; } return s; }"
+assert_error "void value not ignored" "int main() { int x = ({ ; }); return x; }"
+assert_error "expression is not assignable" "int main() { int a = 1, b = 2; (a, b) = 7; return b; }"
+assert_error "lvalue required as unary '&' operand" "int main() { int a = 1, b = 2; int *p = &(a, b); return *p; }"
+assert 24 "int main() { int n = 5; int a[n]; int i; for (i = 0; i < n; i++) a[i] = i; return a[4] + sizeof(a); }"
+assert 71 "int main() { int n = 3; int m = 4; int a[n][m]; a[2][3] = 7; return a[2][3] + sizeof(a) + sizeof(a[0]); }"
+assert 24 "int main() { int n = 6; return sizeof(int[n]); }"
+assert 12 "int main() { int n = 6; return sizeof(char[n][2]); }"
+assert 10 "int main() { int n = 3; int a[2][n]; int (*p)[n] = a; p[1][2] = 9; return a[1][2] + (p + 1 - p); }"
+assert 5 "int main() { int n = 5; typedef char buf[n]; n = 1; buf b; return sizeof(b); }"
+assert 6 "int f(double x) { return x * 2; } int main() { int n = 3; char a[n]; a[0] = 1; return f(2.5) + a[0]; }"
+assert 2 "int sprintf(char *b, char *f, ...); int main() { int n = 5; char a[n]; char buf[32]; sprintf(buf, \"%.1f\", 2.5); return buf[0] - 48; }"
+assert 1 "long sp_here(void); int main() { long before = sp_here(); int i; for (i = 0; i < 10; i++) { int n = 100 + i; char a[n]; a[0] = 0; } return before == sp_here(); }"
+assert 100 "int main() { int i = 0; int s = 0; while (i < 100) { char a[1000000 + i]; a[0] = 1; i++; s = s + a[0]; if (i < 100) continue; } return s; }"
+assert 1 "long sp_here(void); int main() { long before = sp_here(); int i; for (i = 0; i < 10; i++) { int n = 100; char a[n]; if (i == 5) break; } return before == sp_here(); }"
+assert 50 "int main() { int i = 0; again: { char a[1000000 + i]; a[0] = 2; i++; if (i < 50) goto again; } return i; }"
+assert 1 "int main() { int s = 0; for (int i = 0; i < 1000000; i++) s += ({ int n = 50; int a[n]; a[0] = i & 1; a[0]; }); return s == 500000; }"
+assert 1 "long sp_here(void); int main() { long before = sp_here(); int n = 4; { int a[n]; a[0] = 1; { int b[n]; goto out; } } out: return before == sp_here(); }"
+assert 1 "long sp_here(void); int main() { int n = 4; long before; { int a[n]; before = sp_here(); { int b[n]; b[0] = 0; goto out; } out: a[0] = 1; return before == sp_here(); } }"
+assert 7 "int main() { int n = 4; int a[n]; a[1] = 3; { int b[n]; b[1] = 4; a[2] = b[1]; } return a[1] + a[2]; }"
+assert 4 "int main() { int s = 0; for (int i = 0; i < 1000000; i++) { s = 2 * ({ int n = 3; int a[n]; a[0] = i; if (i == 3) break; a[0]; }); } return s; }"
+assert 11 "int main() { char *p = __builtin_alloca(16); p[0] = 5; p[15] = 6; return p[0] + p[15]; }"
+assert 15 "int main() { int *p; int r = 10 + ((p = __builtin_alloca(8)), *p = 5, *p); return r; }"
+assert 7 "int main() { int x = 3; return x + *(int *)({ int *p = __builtin_alloca(4); *p = 4; p; }); }"
+assert 0 "int main() { long *p = __builtin_alloca(24); return (long)p % 16; }"
+assert 9 "int sum(int n, int *a) { int s = 0; int i; for (i = 0; i < n; i++) s = s + a[i]; return s; } int main() { int n = 3; int a[n]; a[0] = 2; a[1] = 3; a[2] = 4; return sum(n, a); }"
+assert_error "jump into the scope of a variable length array" "int main() { int n = 4; goto skip; { int a[n]; skip: a[0] = 1; } return 0; }"
+assert 1 "int main() { struct s { int a : 3; int b : 5; } x; x.a = 3; x.b = -2; return x.a + x.b; }"
+assert 255 "int main() { struct s { int a : 3; } x; x.a = 7; return x.a; }"
+assert 1 "int main() { struct s { int a : 3; } x; return (x.a = 9); }"
+assert 7 "int main() { struct s { unsigned a : 3; } x; x.a = 7; return x.a; }"
+assert 3 "int main() { struct s { unsigned a : 4; unsigned b : 4; } x; x.a = 14; x.b = 5; x.a += 5; return x.a + x.b - 5; }"
+assert 0 "int main() { struct s { unsigned char c; unsigned a : 4; } x; x.a = 15; x.a++; return x.a; }"
+assert 9 "int main() { struct s { char lo; unsigned mid : 6; char hi; } x; x.lo = 4; x.hi = 5; x.mid = 63; return x.lo + x.hi; }"
+assert 5 "struct s { unsigned a : 4; unsigned b : 4; char c; } g = {1, 2, 3}; int main() { return g.a * 16 + g.b + g.c - 16; }"
+assert 33 "struct s { unsigned a : 4; unsigned b : 4; char c; } g = {1, 2, 3}; int main() { return *(char *)&g; }"
+assert 33 "int main() { struct s { unsigned a : 4; unsigned b : 4; char c; } x = {1, 2, 3}; return *(char *)&x; }"
+assert 8 "struct pad { char c; int : 0; char d; unsigned : 3; unsigned e : 2; }; int main() { return sizeof(struct pad); }"
+assert 4 "struct pad { char c; int : 0; char d; }; int main() { struct pad p; return (char *)&p.d - (char *)&p; }"
+assert 1 "int main() { struct s { _Bool b : 1; int rest : 7; } x; x.b = 4; x.rest = 0; return x.b; }"
+assert 1 "struct bits { unsigned a : 3; int b : 5; unsigned c : 30; char d; long e : 40; unsigned char f : 1; }; long bits_size(void); int main() { return sizeof(struct bits) == bits_size(); }"
+assert 1 "struct bits { unsigned a : 3; int b : 5; unsigned c : 30; char d; long e : 40; unsigned char f : 1; }; void fill_bits(struct bits *p); int main() { struct bits b; fill_bits(&b); return b.a == 5 ? b.b == -7 ? b.c == 123456789 ? b.d == 120 ? b.e == -1234567890 ? b.f : 0 : 0 : 0 : 0 : 0; }"
+assert 1 "struct bits { unsigned a : 3; int b : 5; unsigned c : 30; char d; long e : 40; unsigned char f : 1; }; int check_bits(struct bits *p); int main() { struct bits b; b.a = 6; b.b = -3; b.c = 1000; b.d = 7; b.e = 99; b.f = 0; return check_bits(&b); }"
+assert 4 "struct packet { unsigned short len; unsigned char kind : 4; unsigned char flags : 4; char data[]; }; int main() { return sizeof(struct packet); }"
+assert 3 "struct packet { unsigned short len; unsigned char kind : 4; unsigned char flags : 4; char data[]; }; int main() { struct packet *p; return (char *)p->data - (char *)p; }"
+assert 49 "struct packet { unsigned short len; unsigned char kind : 4; unsigned char flags : 4; char data[]; }; void *calloc(long n, long size); int packet_sum(struct packet *p); int main() { struct packet *p = calloc(1, sizeof(struct packet) + 3); p->len = 3; p->kind = 2; p->flags = 1; p->data[0] = 4; p->data[1] = 5; p->data[2] = 7; return packet_sum(p) == 49 ? packet_sum(p) : 0; }"
+assert 8 "struct v { long n; int items[]; }; int main() { return sizeof(struct v); }"
+assert 1 "struct s { unsigned a : 3; } x = {0}; int main() { return x.a - 1 < 0; }"
+assert 0 "struct s { unsigned a : 31; } x = {0}; int main() { return (x.a - 1) > 0; }"
+assert 1 "struct s { unsigned a : 32; } x = {0}; int main() { return (x.a - 1) > 0; }"
+assert 71 "struct s { unsigned a : 3; } x; int main() { x.a = 9; return x.a + (x.a = 15) * 10; }"
+assert_error "initialization of a flexible array member is not supported" "struct s { int n; char d[]; }; struct s g = {3, \"ab\"}; int main() { return g.n; }"
+assert 6 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; int sum_s3(struct s3 s); int main() { struct s3 s = {1, 2, 3}; return sum_s3(s); }"
+assert 9 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct s3 make_s3(int a, int b, int c); int main() { struct s3 s = make_s3(2, 3, 4); return s.a + s.b + s.c; }"
+assert 7 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct s3 make_s3(int a, int b, int c); int main() { return make_s3(5, 7, 9).b; }"
+assert 6 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; double sum_ff(struct ff s); int main() { struct ff s = {1.5, 2.0, 2.5}; return sum_ff(s); }"
+assert 12 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct ff make_ff(float x, float y, float z); int main() { struct ff s = make_ff(2, 4, 6); return s.x + s.y + s.z; }"
+assert 12 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long sum_dl(struct dl s); int main() { struct dl s = {2.5, 10}; return sum_dl(s); }"
+assert 13 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct dl make_dl(double d, long l); int main() { struct dl s = make_dl(3.5, 10); return s.d + s.l; }"
+assert 15 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long sum_fi(struct fi s); int main() { struct fi s = {3.0, 5}; return sum_fi(s); }"
+assert 23 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct fi make_fi(float f, int i); int main() { struct fi s = make_fi(3.0, 20); return s.f + s.i; }"
+assert 65 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long sum_big(struct big s); int main() { struct big s = {5, 4, 0}; return sum_big(s) + 20; }"
+assert 57 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; struct big make_big(long a, long b, long c); int main() { struct big s = make_big(7, 5, 0); return s.a + s.b * 10; }"
+assert 1 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long spill_ll(long a, long b, long c, long d, long e, struct ll s, long f); int main() { struct ll s = {2, 3}; return spill_ll(1, 1, 1, 1, 1, s, 4) == 4325; }"
+assert 1 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long spill_dl(double a, double b, double c, double d, double e, double f, double g, struct dl s, double h); int main() { struct dl s = {2, 3}; return spill_dl(1, 1, 1, 1, 1, 1, 1, s, 1) == 3208; }"
+assert 1 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long ud_bits(union ud u); int main() { union ud u; u.l = 4242; return ud_bits(u) == 4242; }"
+assert 1 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; long apply_big(struct big (*f)(struct big, struct ff)); struct big twice(struct big b, struct ff x) { struct big r = {b.a * 2, b.b * 2 + x.z, b.c * 2 + x.x + x.y}; return r; } int main() { return apply_big(twice) == 862; }"
+assert 17 "struct s3 { char a; char b; char c; }; struct ff { float x; float y; float z; }; struct dl { double d; long l; }; struct fi { float f; int i; }; struct ll { long a; long b; }; struct big { long a; long b; long c; }; union ud { double d; long l; }; double apply_dl(struct dl (*f)(struct s3, struct dl)); struct dl scale(struct s3 a, struct dl b) { struct dl r = {b.d * a.b, b.l * a.c}; return r; } int main() { return apply_dl(scale); }"
+assert 73 "struct p { int x; int y; }; struct p swap(struct p a) { struct p b = {a.y, a.x}; return b; } int main() { struct p a = {3, 7}; a = swap(a); return a.x * 10 + a.y; }"
+assert 7 "struct p { int x; int y; }; struct p swap(struct p a) { struct p b = {a.y, a.x}; return b; } int main() { struct p a = {3, 7}; return swap(a).x; }"
+assert 3 "struct p { int x; int y; }; void clobber(struct p a) { a.x = 100; } int main() { struct p a = {3, 7}; clobber(a); return a.x; }"
+assert 66 "struct b { long v[5]; }; struct b fill(long k) { struct b r; for (int i = 0; i < 5; i++) r.v[i] = k + i; return r; } long total(struct b x) { long s = 0; for (int i = 0; i < 5; i++) s += x.v[i]; return s; } int main() { return total(fill(11)) + fill(1).v[0]; }"
+assert 18 "struct q { char c[3]; }; struct q inc(struct q a, int n) { if (n == 0) return a; a.c[0]++; a.c[1] += 2; a.c[2] += 3; return inc(a, n - 1); } int main() { struct q a = {0, 0, 0}; struct q r = inc(a, 3); return r.c[0] + r.c[1] + r.c[2]; }"
+assert 28 "struct d { double x; double y; }; struct d mid(struct d a, struct d b) { struct d r = {(a.x + b.x) / 2, (a.y + b.y) / 2}; return r; } int main() { struct d a = {2, 10}; struct d b = {4, 40}; struct d m = mid(a, b); return m.x + m.y; }"
+assert 36 "struct w { long a; long b; long c; }; long pick(long x1, long x2, long x3, long x4, long x5, long x6, struct w s, long x7) { return s.a + s.b + s.c + x7 + x1; } int main() { struct w s = {5, 10, 15}; return pick(1, 2, 3, 4, 5, 6, s, 5); }"
+assert 1 "int main() { return _Generic(1, int: 1, long: 2, default: 3); }"
+assert 2 "int main() { long x = 0; return _Generic(x, int: 1, long: 2, default: 3); }"
+assert 3 "int main() { short x = 0; return _Generic(x, int: 1, long: 2, default: 3); }"
+assert 4 "int main() { return _Generic(\"abc\", char *: 4, const char *: 5, default: 0); }"
+assert 1 "int main() { const int c = 0; return _Generic(c, int: 1, const int: 2); }"
+assert 5 "int main() { return _Generic(1.0f, float: 5, double: 6); }"
+assert 6 "int main() { return _Generic(1.0, float: 5, double: 6); }"
+assert 0 "int main() { int x = 0; return _Generic(x++, int: x); }"
+assert 7 "int f(void) { return 0; } int main() { return _Generic(f, int (*)(void): 7, default: 0); }"
+assert 8 "struct p { int x; }; int main() { struct p a; return _Generic(a, struct p: 8, int: 9); }"
+assert 5 "int main() { int x = 0; _Generic(x, int: x, long: 0) = 5; return x; }"
+assert 9 "int main() { int a[3]; return _Generic(a, int *: 9, default: 0); }"
+assert 3 "_Static_assert(sizeof(int) == 4, \"int is 4 bytes\"); int main() { return 3; }"
+assert 4 "int main() { _Static_assert(sizeof(long) == 8, \"long\"); _Static_assert(1); return 4; }"
+assert 8 "struct s { int a; _Static_assert(sizeof(int) == 4, \"in a struct\"); int b; }; int main() { return sizeof(struct s); }"
+assert 5 "_Static_assert(2 > 1, \"ok\"); 5;"
+assert 0 "_Alignas(32) char g; int main() { return (long)&g % 32; }"
+assert 0 "char c1; _Alignas(64) char g; char c2; int main() { return (long)&g % 64; }"
+assert 0 "_Alignas(16) char s[] = \"ab\"; int main() { return (long)s % 16; }"
+assert 8 "_Alignas(double) char c; int main() { return (long)&c % 8 + 8; }"
+assert 0 "int main() { char c; _Alignas(16) char x; return (long)&x % 16; }"
+assert 0 "int main() { char c; _Alignas(32) int x = 7; return (long)&x % 32 + x - 7; }"
+assert 0 "int f(int n) { char c; _Alignas(64) char buf[8]; if (n == 0) return (long)buf % 64; return f(n - 1) + (long)buf % 64; } int main() { return f(5); }"
+assert 28 "long g(long a, long b, long c, long d, long e, long f, long h, long i) { _Alignas(64) long buf[2]; buf[0] = h; buf[1] = i; return (long)buf % 64 + buf[0] + buf[1] + a; } int main() { return g(1, 2, 3, 4, 5, 6, 10, 17); }"
+assert 15 "long sum(int n, ...) { _Alignas(32) char tag; va_list ap; va_start(ap, n); long s = (long)&tag % 32; for (int i = 0; i < n; i++) s += va_arg(ap, long); va_end(ap); return s; } int main() { return sum(8, 1, 1, 1, 1, 1, 1, 4, 5); }"
+assert 32 "struct s { char c; _Alignas(16) int x; }; int main() { return sizeof(struct s); }"
+assert 16 "struct s { char c; _Alignas(16) int x; }; int main() { struct s v; return (char *)&v.x - (char *)&v; }"
+assert 0 "struct s { char c; _Alignas(32) int x; }; int main() { struct s v; v.x = 3; return (long)&v % 32 + v.x - 3; }"
+assert 8 "extern int e; _Alignas(8) int e; int e; int main() { return (long)&e % 8 + 8; }"
+assert 0 "int main() { _Alignas(0) int x = 0; return x; }"
+
+rm tmp2.o
 echo OK